@@ -16,19 +16,23 @@ impl TreeType {
 }
 pub fn ast_generator(output_dir: &str) -> std::io::Result<()> {
     define_ast(
-        &output_dir,
+        output_dir,
         "Expr",
-        vec!["scanner"],
+        vec!["scanner", "stmt"],
         vec![
             "Binary     : Box<Expr> left, Token operator, Box<Expr> right",
-            "Call       : Box<Expr> callee,  Box<Vec<Expr>> arguments",
-            "Assign     : usize id, String name, Box<Expr> value",
+            "Block      : Vec<Stmt> statements",
+            "Call       : Box<Expr> callee, Token paren, Box<Vec<Expr>> arguments",
+            "Assign     : usize id, Token name, Box<Expr> value",
             "Grouping   : Box<Expr> expression",
             "Logical    : Box<Expr> left, Token operator, Box<Expr> right",
+            "Postfix    : usize id, Token name, Token operator",
+            "Prefix     : usize id, Token name, Token operator",
             "Unary      : Token operator, Box<Expr> right",
-            "Variable   : usize id, String name",
+            "Variable   : usize id, Token name",
         ],
         Some(vec![
+        "Integer(i64)",
         "Number(f64)",
         "String(String)",
         "Boolean(bool)",
@@ -37,18 +41,28 @@ pub fn ast_generator(output_dir: &str) -> std::io::Result<()> {
 
     )?;
     define_ast(
-        &output_dir,
+        output_dir,
         "Stmt",
-        vec!["expr", "rc"],
+        vec!["expr", "parser", "scanner", "rc"],
         vec![
-            "Block      : Vec<Stmt> statements",
-            "Expression : Expr expression",
-            "If         : Expr condition, Box<Stmt> then_branch, Option<Box<Stmt>> else_branch",
-            "Function   : String name, Rc<Vec<String>> parameters, Rc<Vec<Stmt>> body",
-            "Print      : Expr expression",
-            "Return     : Option<Expr> value",
-            "Var        : String name, Option<Expr> initializer",
-            "While      : Expr condition, Box<Stmt> body",
+            "Assert     : Token keyword, Expr condition, Option<Expr> message",
+            "Block      : usize line, Vec<Stmt> statements",
+            "Break      : Token keyword, Option<Token> label",
+            "Continue   : Token keyword",
+            "Defer      : Token keyword, Rc<Stmt> body",
+            "DoWhile    : usize line, Box<Stmt> body, Expr condition",
+            "Expression : usize line, Expr expression",
+            "If         : usize line, Expr condition, Box<Stmt> then_branch, Option<Box<Stmt>> else_branch",
+            "Labeled    : Token label, Box<Stmt> body",
+            "Function   : usize line, String name, Rc<Vec<Token>> parameters, Rc<Vec<Stmt>> body",
+            "Print      : usize line, Expr expression",
+            "Return     : Token keyword, Option<Expr> value",
+            "Switch     : Token keyword, Expr subject, Vec<CaseClause> cases, Option<Vec<Stmt>> default",
+            "Throw      : Token keyword, Expr value",
+            "Try        : Token keyword, Vec<Stmt> body, Token name, Vec<Stmt> handler",
+            "Var        : usize line, String name, Option<Expr> initializer",
+            "For        : usize line, Option<Box<Stmt>> initializer, Option<Expr> condition, Option<Expr> increment, Box<Stmt> body, Option<Box<Stmt>> else_branch",
+            "While      : usize line, Expr condition, Box<Stmt> body, Option<Box<Stmt>> else_branch",
         ],
         None,
     )?;
@@ -66,9 +80,9 @@ fn define_ast(
     let mut tree_types: Vec<TreeType> = Vec::new();
     for import in imports {
         if import.eq("rc") {
-            write!(file, "use std::rc::Rc;\n")?;
+            writeln!(file, "use std::rc::Rc;")?;
         }else{
-        write!(file, "use crate::{}::*;\n", import)?;
+        writeln!(file, "use crate::{}::*;", import)?;
         }
     }
     write!(file, "\n\n")?;
@@ -86,17 +100,23 @@ fn define_ast(
             .collect();
         tree_types.push(TreeType::new(base_name.to_string(), fields))
     }
-    write!(file, "#[derive(Debug, PartialEq)]\n")?;
-    write!(file, "pub enum {} {{\n", filename)?;
+    writeln!(file, "#[derive(Debug, PartialEq)]")?;
+    // every node is a JSON object with a "kind" field next to its own fields
+    writeln!(file, "#[cfg_attr(feature = \"serde\", derive(serde::Serialize, serde::Deserialize), serde(tag = \"kind\"))]")?;
+    writeln!(file, "pub enum {} {{", filename)?;
     if let Some(literal) = literals{
     for lit in &literal {
-        write!(file, "\t{},\n",lit)?;
+        // a tagged newtype variant must serialize as an object, so the literal goes under "value"
+        if lit.contains('(') {
+            writeln!(file, "\t#[cfg_attr(feature = \"serde\", serde(with = \"crate::parser::literal\"))]")?;
+        }
+        writeln!(file, "\t{},",lit)?;
     }
     }
     for t in &tree_types {
-        write!(file, "\t{}{{\n",t.base_name)?;
+        writeln!(file, "\t{}{{",t.base_name)?;
         for f in &t.fields {
-            write!(file, "\t {},\n", f)?;
+            writeln!(file, "\t {},", f)?;
         }
     write!(file, "\t}},\n\n")?;
     }
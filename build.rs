@@ -3,5 +3,35 @@ mod ast_generator;
 use ast_generator::*;
 
 fn main() -> Result<()> {
-    ast_generator("src")
+    ast_generator("src")?;
+    #[cfg(feature = "ffi")]
+    generate_header();
+    Ok(())
+}
+
+// keeps include/rlox.h in step with the extern functions in src/ffi.rs
+#[cfg(feature = "ffi")]
+fn generate_header() {
+    // naming one file stops cargo rerunning on any change, so the AST generator's inputs are listed too
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=ast_generator");
+    let config = cbindgen::Config {
+        language: cbindgen::Language::C,
+        header: Some("/* C API for embedding rlox, see src/ffi.rs. Build with `cargo build --features ffi`. */".to_string()),
+        autogen_warning: Some("/* Generated by cbindgen from src/ffi.rs, don't edit by hand. */".to_string()),
+        include_guard: Some("RLOX_H".to_string()),
+        sys_includes: vec!["stddef.h".to_string(), "stdint.h".to_string()],
+        no_includes: true,
+        usize_is_size_t: true,
+        documentation_style: cbindgen::DocumentationStyle::C,
+        ..Default::default()
+    };
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("cargo sets CARGO_MANIFEST_DIR");
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(format!("{}/src/ffi.rs", crate_dir))
+        .generate()
+        .expect("src/ffi.rs should be valid for cbindgen")
+        .write_to_file(format!("{}/include/rlox.h", crate_dir));
 }
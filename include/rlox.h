@@ -0,0 +1,55 @@
+/* C API for embedding rlox, see src/ffi.rs. Build with `cargo build --features ffi`. */
+
+#ifndef RLOX_H
+#define RLOX_H
+
+/* Generated by cbindgen from src/ffi.rs, don't edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct Lox Lox;
+
+/*
+ Creates an interpreter, to be released with `rlox_free`.
+ */
+struct Lox *rlox_new(void);
+
+/*
+ Releases an interpreter. NULL is ignored.
+
+ # Safety
+
+ `lox` must be NULL or come from `rlox_new`, and must not be used afterwards.
+ */
+void rlox_free(struct Lox *lox);
+
+/*
+ Runs `source_len` bytes of UTF-8 source, returning 0 on success and 1 on
+ any error, whose message `rlox_get_error` then gives.
+
+ # Safety
+
+ `lox` must come from `rlox_new`, and `source` must point to `source_len` readable bytes.
+ */
+int32_t rlox_run(struct Lox *lox, const char *source, size_t source_len);
+
+/*
+ What the last `rlox_run` printed, empty before the first run.
+
+ # Safety
+
+ `lox` must be NULL or come from `rlox_new`. NULL gives NULL.
+ */
+const char *rlox_get_output(struct Lox *lox);
+
+/*
+ The error message of the last `rlox_run`, or NULL if it succeeded.
+
+ # Safety
+
+ `lox` must be NULL or come from `rlox_new`. NULL gives NULL.
+ */
+const char *rlox_get_error(struct Lox *lox);
+
+#endif  /* RLOX_H */
@@ -1,60 +1,207 @@
-use crate::{expr::*, error::RloxError};
-use crate::scanner::*;
+use crate::expr::*;
+use crate::stmt::*;
 
 pub struct AstPrinter {}
 
-impl ExprVisitor<String> for AstPrinter {
-    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<String, RloxError> {
-        self.parenthesize(
-            String::from_utf8(expr.operator.lexeme.to_vec()).unwrap(),
-            vec![expr.left.as_ref(), expr.right.as_ref()],
-        )
-    }
-
-    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<String, RloxError>{
-        self.parenthesize("grouping".to_string(), vec![&expr.expression])
+impl AstPrinter {
+    pub fn print(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Integer(value) => value.to_string(),
+            Expr::Number(value) => value.to_string(),
+            Expr::String(value) => value.to_string(),
+            Expr::Boolean(value) => value.to_string(),
+            Expr::Nil => "nil".to_string(),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            }
+            | Expr::Logical {
+                left,
+                operator,
+                right,
+            } => self.parenthesize(&operator.lexeme, &[left, right]),
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let mut expressions = vec![callee.as_ref()];
+                expressions.extend(arguments.iter());
+                self.parenthesize("call", &expressions)
+            }
+            Expr::Assign { name, value, .. } => {
+                format!("(= {} {})", name.lexeme, self.print(value))
+            }
+            Expr::Grouping { expression } => self.parenthesize("group", &[expression]),
+            Expr::Unary { operator, right } => self.parenthesize(&operator.lexeme, &[right]),
+            Expr::Variable { name, .. } => name.lexeme.to_string(),
+            Expr::Postfix { name, operator, .. } => format!("(postfix{} {})", operator.lexeme, name.lexeme),
+            Expr::Prefix { name, operator, .. } => format!("(prefix{} {})", operator.lexeme, name.lexeme),
+            Expr::Block { statements } => self.block("block", statements),
+        }
     }
 
-    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<String,RloxError> {
-        if let Some(value) = &expr.value {
-            match value {
-                Literal::Identifier(val) => Ok( val.to_string() ),
-                Literal::Str(val) => Ok(val.to_string()),
-                Literal::Number(val) => Ok(val.to_string()),
-                Literal::True => Ok("true".to_string()),
-                Literal::False => Ok("false".to_string()),
-                Literal::Nil => Ok("nil".to_string())
+    pub fn print_stmt(&self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Assert {
+                condition, message, ..
+            } => match message {
+                Some(message) => {
+                    format!("(assert {} {})", self.print(condition), self.print(message))
+                }
+                None => format!("(assert {})", self.print(condition)),
+            },
+            Stmt::Block { statements, .. } => self.block("block", statements),
+            Stmt::Break { label, .. } => match label {
+                Some(label) => format!("(break {})", label.lexeme),
+                None => "(break)".to_string(),
+            },
+            Stmt::Continue { .. } => "(continue)".to_string(),
+            Stmt::Defer { body, .. } => format!("(defer {})", self.print_stmt(body)),
+            Stmt::Expression { expression, .. } => format!("(; {})", self.print(expression)),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => match else_branch {
+                Some(else_branch) => format!(
+                    "(if {} {} {})",
+                    self.print(condition),
+                    self.print_stmt(then_branch),
+                    self.print_stmt(else_branch)
+                ),
+                None => format!(
+                    "(if {} {})",
+                    self.print(condition),
+                    self.print_stmt(then_branch)
+                ),
+            },
+            Stmt::Labeled { label, body } => {
+                format!("(label {} {})", label.lexeme, self.print_stmt(body))
+            }
+            Stmt::Function {
+                name,
+                parameters,
+                body,
+                ..
+            } => format!(
+                "(fun {} ({}) {})",
+                name,
+                parameters
+                    .iter()
+                    .map(|parameter| parameter.lexeme.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                self.block("block", body)
+            ),
+            Stmt::Print { expression, .. } => format!("(print {})", self.print(expression)),
+            Stmt::Return { value, .. } => match value {
+                Some(value) => format!("(return {})", self.print(value)),
+                None => "(return)".to_string(),
+            },
+            Stmt::Throw { value, .. } => format!("(throw {})", self.print(value)),
+            Stmt::Try {
+                body,
+                name,
+                handler,
+                ..
+            } => format!(
+                "(try {} {})",
+                self.block("block", body),
+                self.block(&format!("catch {}", name.lexeme), handler)
+            ),
+            Stmt::Var {
+                name, initializer, ..
+            } => match initializer {
+                Some(initializer) => format!("(var {} {})", name, self.print(initializer)),
+                None => format!("(var {})", name),
+            },
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                else_branch,
+                ..
+            } => format!(
+                "(for {} {} {} {}{})",
+                initializer
+                    .as_ref()
+                    .map_or("nil".to_string(), |init| self.print_stmt(init)),
+                condition
+                    .as_ref()
+                    .map_or("nil".to_string(), |cond| self.print(cond)),
+                increment
+                    .as_ref()
+                    .map_or("nil".to_string(), |incr| self.print(incr)),
+                self.print_stmt(body),
+                self.print_else(else_branch)
+            ),
+            Stmt::DoWhile {
+                body, condition, ..
+            } => format!("(do {} {})", self.print_stmt(body), self.print(condition)),
+            Stmt::Switch {
+                subject,
+                cases,
+                default,
+                ..
+            } => {
+                let mut output = format!("(switch {}", self.print(subject));
+                for case in cases {
+                    output.push(' ');
+                    let label = match &case.guard {
+                        Some(guard) => format!("case {} if {}", self.print(&case.value), self.print(guard)),
+                        None => format!("case {}", self.print(&case.value)),
+                    };
+                    output.push_str(&self.block(&label, &case.body));
+                }
+                if let Some(default) = default {
+                    output.push(' ');
+                    output.push_str(&self.block("default", default));
+                }
+                output.push(')');
+                output
+            }
+            Stmt::While {
+                condition,
+                body,
+                else_branch,
+                ..
+            } => {
+                format!(
+                    "(while {} {}{})",
+                    self.print(condition),
+                    self.print_stmt(body),
+                    self.print_else(else_branch)
+                )
             }
-        } else {
-            Ok("nil".to_string())
         }
     }
 
-    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<String,RloxError> {
-        self.parenthesize(
-            String::from_utf8(expr.operator.lexeme.to_vec()).unwrap(),
-            vec![expr.right.as_ref()],
-        )
+    // a loop's `else`, printed after its body like an if's
+    fn print_else(&self, else_branch: &Option<Box<Stmt>>) -> String {
+        else_branch
+            .as_ref()
+            .map_or(String::new(), |stmt| format!(" {}", self.print_stmt(stmt)))
     }
 
-    fn visit_variable_expr(&self, variable: &VariableExpr) -> Result<String, RloxError> {
-        todo!()
-    }
-}
-impl AstPrinter {
-    pub fn print(&self, expr: &Expr) -> Result<String,RloxError> {
-        expr.accept(self)
+    fn block(&self, name: &str, statements: &[Stmt]) -> String {
+        let mut output = format!("({}", name);
+        for statement in statements {
+            output.push(' ');
+            output.push_str(&self.print_stmt(statement));
+        }
+        output.push(')');
+        output
     }
 
-    fn parenthesize(&self, name: String, expressions: Vec<&Expr>) -> Result<String,RloxError> {
-        let mut output = String::new();
-        output.push_str(&format!("({}", name));
+    fn parenthesize(&self, name: &str, expressions: &[&Expr]) -> String {
+        let mut output = format!("({}", name);
         for expr in expressions {
-            let expression = expr.accept(self)?;
-            output.push_str(" ");
-            output.push_str(&expression);
+            output.push(' ');
+            output.push_str(&self.print(expr));
         }
-            output.push_str(")");
-        Ok(output)
+        output.push(')');
+        output
     }
 }
@@ -6,33 +6,76 @@ use std::time::UNIX_EPOCH;
 use crate::environment::*;
 use crate::error::*;
 use crate::interpreter::*;
+use crate::optimizer::fold_constants;
+use crate::parser::*;
+use crate::resolver::*;
+use crate::scanner::Token;
 use crate::stmt::*;
 
 #[derive(Debug, Clone)]
 pub struct RloxFunction {
-    parameters: Rc<Vec<String>>,
+    name: String,
+    parameters: Rc<Vec<Token>>,
     body: Rc<Vec<Stmt>>,
     closure: Rc<RefCell<Environment>>,
 }
 
+// how many arguments a callable takes, checked by the interpreter before `call`
+// only natives use `AtLeast` and `Variadic`, Lox functions are always `Fixed` to their parameter count
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Fixed(usize),
+    AtLeast(usize),
+    Variadic,
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            Arity::Fixed(arity) => count == *arity,
+            Arity::AtLeast(arity) => count >= *arity,
+            Arity::Variadic => true,
+        }
+    }
+}
+
+impl std::fmt::Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arity::Fixed(arity) => write!(f, "{}", arity),
+            Arity::AtLeast(arity) => write!(f, "at least {}", arity),
+            Arity::Variadic => write!(f, "any number of"),
+        }
+    }
+}
+
 pub trait RloxCallable {
     fn call(&self, interpreter: &mut Interpreter, args: &[Rc<Value>]) -> Result<Rc<Value>, RloxError>;
-    fn arity(&self) -> usize;
+    fn arity(&self) -> Arity;
+    // shown when the function is printed, `<fn name>`, or `<fn>` without one
+    fn name(&self) -> Option<&str> {
+        None
+    }
 }
 
 impl std::fmt::Debug for dyn RloxCallable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "<lox fn>")
+        match self.name() {
+            Some(name) => write!(f, "<fn {}>", name),
+            None => write!(f, "<fn>"),
+        }
     }
 }
 
 impl RloxFunction {
     pub fn new(
-        parameters: Rc<Vec<String>>,
+        name: String,
+        parameters: Rc<Vec<Token>>,
         body: Rc<Vec<Stmt>>,
         closure: Rc<RefCell<Environment>>,
     ) -> Self {
         Self {
+            name,
             parameters,
             body,
             closure,
@@ -42,12 +85,14 @@ impl RloxFunction {
 
 impl RloxCallable for RloxFunction {
     fn call(&self, interpreter: &mut Interpreter, args: &[Rc<Value>]) -> Result<Rc<Value>, RloxError> {
-        let environment = Environment::new(Rc::clone(&self.closure));
+        let mut environment = Environment::new(Rc::clone(&self.closure));
 
         for (token, val) in self.parameters.iter().zip(args.iter()) {
-            environment.define(&token.to_string(), val.clone())
+            environment.define(&token.lexeme, val.clone())
         }
-        if let Err(err) = interpreter.execute_block(&self.body, Rc::new(RefCell::new(environment))) {
+        if let Err(err) =
+            interpreter.execute_function_body(&self.body, Rc::new(RefCell::new(environment)))
+        {
             match err {
                 RloxError::Return(val) => Ok(Rc::new(val)),
                 e => Err(e),
@@ -57,8 +102,37 @@ impl RloxCallable for RloxFunction {
         }
     }
 
-    fn arity(&self) -> usize {
-        self.parameters.len()
+    fn arity(&self) -> Arity {
+        Arity::Fixed(self.parameters.len())
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+}
+
+// the body of a native registered with `Interpreter::define_native`, it can call back
+// into Lox through `Interpreter::call_function`
+pub type NativeFn = dyn Fn(&mut Interpreter, &[Rc<Value>]) -> Result<Rc<Value>, RloxError>;
+
+#[derive(Clone)]
+pub struct Native {
+    pub name: String,
+    pub arity: Arity,
+    pub function: Rc<NativeFn>,
+}
+
+impl RloxCallable for Native {
+    fn call(&self, interpreter: &mut Interpreter, args: &[Rc<Value>]) -> Result<Rc<Value>, RloxError> {
+        (self.function)(interpreter, args)
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
     }
 }
 
@@ -73,12 +147,404 @@ impl RloxCallable for Clock {
         Ok(Rc::new(Value::Number(since_the_epoch.as_millis() as f64)))
     }
 
-    fn arity(&self) -> usize {
-        0
+    fn arity(&self) -> Arity {
+        Arity::Fixed(0)
     }
 }
 
+// `panic(message)` stops the whole program, `try` can't catch it
+#[derive(Debug, Clone)]
+pub struct Panic {}
+
+impl RloxCallable for Panic {
+    fn call(&self, _interpreter: &mut Interpreter, args: &[Rc<Value>]) -> Result<Rc<Value>, RloxError> {
+        Err(RloxError::Panic {
+            line: None,
+            message: args[0].to_string(),
+        })
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::Fixed(1)
+    }
+}
+
+// runs Lox source in the global scope, evaluating to its trailing expression
+#[derive(Debug, Clone)]
+pub struct Eval {}
+
+impl RloxCallable for Eval {
+    fn call(&self, interpreter: &mut Interpreter, args: &[Rc<Value>]) -> Result<Rc<Value>, RloxError> {
+        let Value::Str(source) = args[0].as_ref() else {
+            return Err(RloxError::NativeError {
+                message: format!("eval expects a string, got {}.", args[0].type_name()),
+            });
+        };
+        let mut statements = Parser::from_source(source)
+            .map_err(|mut errors| errors.remove(0))
+            .and_then(|mut parser| parser.parse_with_trailing_expression())
+            .map_err(|err| match err {
+                RloxError::ParseError { token, message, .. } => RloxError::NativeError {
+                    message: format!("eval failed at {}: {}", token, message),
+                },
+                RloxError::ScanError { message, .. }
+                | RloxError::UnterminatedStringError { message, .. } => RloxError::NativeError {
+                    message: format!("eval failed: {}", message),
+                },
+                err => err,
+            })?;
+        fold_constants(&mut statements);
+        Resolver::new(interpreter)
+            .resolve(&statements)
+            .and_then(|_| interpreter.eval(&statements))
+            .map_err(|error| RloxError::InEval {
+                source: source.clone(),
+                error: Box::new(error),
+            })
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::Fixed(1)
+    }
+}
+
+// `format("{} is {}", name, age)`, one argument per placeholder and `{{`/`}}` for literal braces,
+// `escape_html` escapes `&`, `<` and `>` in the arguments
+#[derive(Debug, Clone)]
+pub struct Format {
+    pub escape_html: bool,
+}
+
+impl RloxCallable for Format {
+    fn call(&self, _interpreter: &mut Interpreter, args: &[Rc<Value>]) -> Result<Rc<Value>, RloxError> {
+        let Value::Str(template) = args[0].as_ref() else {
+            return Err(RloxError::NativeError {
+                message: format!("format expects a string template, got {}.", args[0].type_name()),
+            });
+        };
+        let arguments = &args[1..];
+        let mut placeholders = 0;
+        let mut output = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    chars.next();
+                    output.push(c);
+                }
+                ('{', Some('}')) => {
+                    chars.next();
+                    if let Some(argument) = arguments.get(placeholders) {
+                        let argument = argument.to_string();
+                        if self.escape_html {
+                            output.push_str(&escape_html(&argument));
+                        } else {
+                            output.push_str(&argument);
+                        }
+                    }
+                    placeholders += 1;
+                }
+                _ => output.push(c),
+            }
+        }
+        if placeholders != arguments.len() {
+            return Err(RloxError::NativeError {
+                message: format!(
+                    "format has {} placeholders but got {} arguments.",
+                    placeholders,
+                    arguments.len()
+                ),
+            });
+        }
+        Ok(Rc::new(Value::Str(output.into())))
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::AtLeast(1)
+    }
+}
+
+// `min(a, b, ...)` or `max(a, b, ...)` over two or more numbers, giving back the winning argument
+// so integers stay integers
+#[derive(Debug, Clone)]
+pub struct Extreme {
+    pub max: bool,
+}
+
+impl RloxCallable for Extreme {
+    fn call(&self, _interpreter: &mut Interpreter, args: &[Rc<Value>]) -> Result<Rc<Value>, RloxError> {
+        let name = if self.max { "max" } else { "min" };
+        let mut extreme: Option<(f64, &Rc<Value>)> = None;
+        for arg in args {
+            let number = match arg.as_ref() {
+                Value::Integer(number) => *number as f64,
+                Value::Number(number) => *number,
+                _ => {
+                    return Err(RloxError::NativeError {
+                        message: format!("{} expects numbers, got {}.", name, arg.type_name()),
+                    })
+                }
+            };
+            extreme = match extreme {
+                Some((current, _)) if self.max && number > current => Some((number, arg)),
+                Some((current, _)) if !self.max && number < current => Some((number, arg)),
+                Some(current) => Some(current),
+                None => Some((number, arg)),
+            };
+        }
+        let (_, value) = extreme.expect("arity guarantees two arguments");
+        Ok(value.clone())
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::AtLeast(2)
+    }
+}
+
+// natives over `Value::Bytes`, each variant is registered under its `name`
+#[derive(Debug, Clone, Copy)]
+pub enum BytesNative {
+    // `read_file_bytes(path)`
+    ReadFile,
+    // `write_file_bytes(path, bytes)`
+    WriteFile,
+    // `bytes_len(bytes)`
+    Len,
+    // `bytes_get(bytes, index)`, the byte as a number
+    Get,
+    // `bytes_to_string(bytes)`, failing on invalid UTF-8
+    ToString,
+    // `bytes_from_string(string)`, its UTF-8 encoding
+    FromString,
+}
+
+impl BytesNative {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BytesNative::ReadFile => "read_file_bytes",
+            BytesNative::WriteFile => "write_file_bytes",
+            BytesNative::Len => "bytes_len",
+            BytesNative::Get => "bytes_get",
+            BytesNative::ToString => "bytes_to_string",
+            BytesNative::FromString => "bytes_from_string",
+        }
+    }
+
+    fn error(&self, message: String) -> RloxError {
+        RloxError::NativeError {
+            message: format!("{} {}", self.name(), message),
+        }
+    }
+
+    fn bytes<'a>(&self, value: &'a Value) -> Result<&'a Rc<Vec<u8>>, RloxError> {
+        match value {
+            Value::Bytes(bytes) => Ok(bytes),
+            _ => Err(self.error(format!("expects bytes, got {}.", value.type_name()))),
+        }
+    }
+
+    fn string<'a>(&self, value: &'a Value) -> Result<&'a str, RloxError> {
+        match value {
+            Value::Str(string) => Ok(string),
+            _ => Err(self.error(format!("expects a string, got {}.", value.type_name()))),
+        }
+    }
+}
+
+impl RloxCallable for BytesNative {
+    fn call(&self, _interpreter: &mut Interpreter, args: &[Rc<Value>]) -> Result<Rc<Value>, RloxError> {
+        let value = match self {
+            BytesNative::ReadFile => {
+                let path = self.string(&args[0])?;
+                let bytes = std::fs::read(path).map_err(|err| self.error(format!("failed: {}.", err)))?;
+                Value::Bytes(Rc::new(bytes))
+            }
+            BytesNative::WriteFile => {
+                let path = self.string(&args[0])?;
+                let bytes = self.bytes(&args[1])?;
+                std::fs::write(path, bytes.as_slice())
+                    .map_err(|err| self.error(format!("failed: {}.", err)))?;
+                Value::Nil
+            }
+            BytesNative::Len => Value::Integer(self.bytes(&args[0])?.len() as i64),
+            BytesNative::Get => {
+                let bytes = self.bytes(&args[0])?;
+                let index = match args[1].as_ref() {
+                    Value::Integer(index) => *index,
+                    Value::Number(index) if index.fract() == 0.0 => *index as i64,
+                    index => {
+                        return Err(self.error(format!("expects a whole number index, got {}.", index)))
+                    }
+                };
+                let byte = usize::try_from(index).ok().and_then(|index| bytes.get(index));
+                match byte {
+                    Some(byte) => Value::Integer(*byte as i64),
+                    None => {
+                        return Err(self.error(format!(
+                            "index {} is out of range for {} bytes.",
+                            index,
+                            bytes.len()
+                        )))
+                    }
+                }
+            }
+            BytesNative::ToString => {
+                let bytes = self.bytes(&args[0])?;
+                let string = String::from_utf8(bytes.to_vec())
+                    .map_err(|_| self.error("expects valid UTF-8.".to_string()))?;
+                Value::Str(string.into())
+            }
+            BytesNative::FromString => {
+                Value::Bytes(Rc::new(self.string(&args[0])?.as_bytes().to_vec()))
+            }
+        };
+        Ok(Rc::new(value))
+    }
+
+    fn arity(&self) -> Arity {
+        match self {
+            BytesNative::WriteFile | BytesNative::Get => Arity::Fixed(2),
+            _ => Arity::Fixed(1),
+        }
+    }
+}
+
+// what a deserialized `Value::Func` holds, only its name survives serialization
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone)]
+pub struct DeserializedFunction {
+    pub name: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl RloxCallable for DeserializedFunction {
+    fn call(&self, _interpreter: &mut Interpreter, _args: &[Rc<Value>]) -> Result<Rc<Value>, RloxError> {
+        Err(RloxError::NativeError {
+            message: format!(
+                "{:?} was deserialized and can't be called.",
+                self as &dyn RloxCallable
+            ),
+        })
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::Variadic
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 #[derive(Debug, Clone)]
 pub struct RloxClass {
     pub name: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::Value;
+    use crate::test_interpreter::TestInterpreter;
+
+    fn printed(source: &str) -> Vec<String> {
+        let mut lox = TestInterpreter::new();
+        lox.run(source).unwrap();
+        lox.printed_lines().to_vec()
+    }
+
+    fn native_error(source: &str) -> String {
+        TestInterpreter::new().run(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn eval_gives_back_the_trailing_expression() {
+        assert_eq!(printed("print eval(\"1 + 2\");"), ["3"]);
+        assert_eq!(printed("var a = 2; print eval(\"var b = 3; a * b\");"), ["6"]);
+        assert_eq!(printed("eval(\"var c = 1;\"); print c;"), ["1"]);
+    }
+
+    #[test]
+    fn eval_reports_bad_source() {
+        assert_eq!(native_error("eval(\"1 +\");"), "[line 1] RuntimeError: eval failed at Eof: Expect expression.");
+        assert_eq!(native_error("eval(1);"), "[line 1] RuntimeError: eval expects a string, got number.");
+    }
+
+    #[test]
+    fn format_html_escapes_arguments() {
+        assert_eq!(
+            printed("print format(\"no placeholders\"); print formatHtml(\"<b>{}</b>\", \"a<b & c>\");"),
+            ["no placeholders", "<b>a&lt;b &amp; c&gt;</b>"]
+        );
+    }
+
+    #[test]
+    fn format_fills_placeholders() {
+        assert_eq!(
+            printed("var name = \"Ada\"; print format(\"{} is {} years old, {{{}}}\", name, 36, nil);"),
+            ["Ada is 36 years old, {nil}"]
+        );
+        assert_eq!(
+            native_error("format(\"{} {}\", 1);"),
+            "[line 1] RuntimeError: format has 2 placeholders but got 1 arguments."
+        );
+    }
+
+    #[test]
+    fn min_and_max_keep_the_winning_argument() {
+        assert_eq!(
+            printed("print min(3, 1.5); print min(3, 1, 2); print max(1, 4, 2.5, -1); print max(2.5, 2);"),
+            ["1.5", "1", "4", "2.5"]
+        );
+        assert_eq!(
+            native_error("max(1, nil);"),
+            "[line 1] RuntimeError: max expects numbers, got nil."
+        );
+        assert_eq!(
+            native_error("min(1);"),
+            "[line 1] RuntimeError: Expected at least 2 arguments but got 1."
+        );
+    }
+
+    #[test]
+    fn reads_bytes_from_a_file() {
+        let path = std::env::temp_dir().join(format!("rlox-bytes-{}.bin", std::process::id()));
+        std::fs::write(&path, [0x00, 0xff, 0x41]).unwrap();
+        let mut lox = TestInterpreter::new();
+        lox.set_global("path", Value::Str(path.display().to_string().into()));
+        let result = lox.run(
+            "var b = read_file_bytes(path); print b; print bytes_len(b);
+            print bytes_get(b, 0); print bytes_get(b, 1); print bytes_get(b, 2);
+            bytes_to_string(b);",
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(lox.printed_lines(), ["<3 bytes>", "3", "0", "255", "65"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "[line 3] RuntimeError: bytes_to_string expects valid UTF-8."
+        );
+        assert_eq!(
+            native_error("bytes_get(bytes_from_string(\"ab\"), 2);"),
+            "[line 1] RuntimeError: bytes_get index 2 is out of range for 2 bytes."
+        );
+    }
+
+    #[test]
+    fn bytes_round_trip_utf8() {
+        let path = std::env::temp_dir().join(format!("rlox-bytes-round-trip-{}.bin", std::process::id()));
+        let mut lox = TestInterpreter::new();
+        lox.set_global("path", Value::Str(path.display().to_string().into()));
+        lox.run(
+            "var b = bytes_from_string(\"héllo\"); print bytes_len(b); print bytes_to_string(b);
+            write_file_bytes(path, b); print bytes_to_string(read_file_bytes(path)) == \"héllo\";",
+        )
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(lox.printed_lines(), ["6", "héllo", "true"]);
+    }
+}
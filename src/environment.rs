@@ -1,82 +1,258 @@
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     rc::Rc,
 };
 
-use crate::{error::*, interpreter::*};
+use crate::{error::*, interpreter::*, scanner::Token};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Environment {
     pub enclosing: Option<Rc<RefCell<Environment>>>,
-    pub values: RefCell<HashMap<String, Rc<Value>>>,
-}
-
-impl Default for Environment {
-    fn default() -> Self {
-        Self {
-            enclosing: None,
-            values: RefCell::new(HashMap::new()),
-        }
-    }
+    pub values: HashMap<String, Rc<Value>>,
+    // declared with `var x;` and not assigned since, reading them is an error
+    uninitialized: HashSet<String>,
 }
 
 impl Environment {
     pub fn new(enclosing: Rc<RefCell<Environment>>) -> Environment {
         Self {
             enclosing: Some(enclosing),
-            values: RefCell::new(HashMap::new()),
+            values: HashMap::new(),
+            uninitialized: HashSet::new(),
         }
     }
-    pub fn define(&self, name: &str, value: Rc<Value>) {
-        self.values
-            .borrow_mut()
-            .insert(name.to_string(), value);
+    pub fn define(&mut self, name: &str, value: Rc<Value>) {
+        self.uninitialized.remove(name);
+        self.values.insert(name.to_string(), value);
+    }
+
+    // defines `name` as nil but errors on reads until it's assigned
+    pub fn declare(&mut self, name: &str) {
+        self.values.insert(name.to_string(), Rc::new(Value::Nil));
+        self.uninitialized.insert(name.to_string());
     }
-    pub fn get_at(&self, distance: usize, token: &str) -> Result<Rc<Value>, RloxError> {
-        if 0 ==distance {
-            {
-                self.get(token)
+    // the scope exactly `distance` hops out from `environment`, which is 0 hops,
+    // `token` is the variable being looked up, for the error if that's past the global scope
+    pub fn ancestor(
+        environment: &Rc<RefCell<Environment>>,
+        distance: usize,
+        token: &Token,
+    ) -> Result<Rc<RefCell<Environment>>, RloxError> {
+        let mut ancestor = environment.clone();
+        for _ in 0..distance {
+            let enclosing = ancestor.borrow().enclosing.clone();
+            ancestor = enclosing.ok_or_else(|| Self::past_global(token))?;
+        }
+        Ok(ancestor)
+    }
+
+    // a resolver bug, but not one worth crashing the host over
+    fn past_global(token: &Token) -> RloxError {
+        RloxError::RuntimeError {
+            lexeme: token.lexeme.clone(),
+            line: token.line,
+            message: format!("Variable '{}' was resolved past the global scope.", token.lexeme),
+            span: Some((token.start_byte, token.end_byte)),
+        }
+    }
+
+    // reads the variable from the scope the resolver found it in, ignoring any others
+    pub fn get_at(&self, distance: usize, token: &Token) -> Result<Rc<Value>, RloxError> {
+        match &self.enclosing {
+            _ if distance == 0 => self.get_local(token),
+            Some(enclosing) => {
+                let ancestor = Self::ancestor(enclosing, distance - 1, token)?;
+                let value = ancestor.borrow().get_local(token);
+                value
             }
-        } else {
-            self.enclosing
-                .as_ref()
-                .unwrap()
-                .borrow()
-                .get_at(distance - 1, token)
+            None => Err(Self::past_global(token)),
         }
     }
 
-    pub fn get(&self, token: &str) -> Result<Rc<Value>, RloxError> {
+    pub fn get(&self, token: &Token) -> Result<Rc<Value>, RloxError> {
+        match &self.enclosing {
+            Some(enclosing) if !self.values.contains_key(&token.lexeme) => {
+                enclosing.borrow().get(token)
+            }
+            _ => self.get_local(token),
+        }
+    }
 
-        match self.values.borrow().get(token) {
+    fn get_local(&self, token: &Token) -> Result<Rc<Value>, RloxError> {
+        match self.values.get(&token.lexeme) {
+            Some(_) if self.uninitialized.contains(&token.lexeme) => Err(RloxError::RuntimeError {
+                lexeme: token.lexeme.clone(),
+                line: token.line,
+                message: format!("Variable '{}' used before assignment.", token.lexeme),
+                span: Some((token.start_byte, token.end_byte)),
+            }),
             Some(val) => Ok(val.clone()),
-            None => match &self.enclosing {
-                Some(enclosing) => enclosing.borrow().get(token),
-                None => Err(RloxError::RuntimeError {
-                    lexeme: token.to_string(),
-                    message: format!("Trying to get undefined variable {}.", &token),
-                }),
-            },
+            None => Err(RloxError::RuntimeError {
+                lexeme: token.lexeme.clone(),
+                line: token.line,
+                message: format!("Trying to get undefined variable {}.", token.lexeme),
+                span: Some((token.start_byte, token.end_byte)),
+            }),
         }
     }
     pub fn assign_at(
         &mut self,
         distance: &usize,
-        token: &str,
+        token: &Token,
         value: Rc<Value>,
     ) -> Result<(), RloxError> {
+        match &self.enclosing {
+            _ if *distance == 0 => self.assign_local(token, value),
+            Some(enclosing) => {
+                let ancestor = Self::ancestor(enclosing, distance - 1, token)?;
+                ancestor.borrow_mut().assign_local(token, value);
+            }
+            None => return Err(Self::past_global(token)),
+        }
+        Ok(())
+    }
+
+    fn assign_local(&mut self, token: &Token, value: Rc<Value>) {
+        self.uninitialized.remove(&token.lexeme);
+        self.values.insert(token.lexeme.clone(), value);
+    }
 
-        if 0.eq(distance) {
-            self.values.borrow_mut().insert(token.to_string(), value.clone());
-            Ok(())
-        } else {
-            self.enclosing
-                .as_ref()
-                .unwrap()
-                .borrow_mut()
-                .assign_at(&(distance - 1), token, value)
+    // every visible variable, inner scopes shadowing outer ones
+    pub fn snapshot(&self) -> HashMap<String, Value> {
+        let mut snapshot = match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().snapshot(),
+            None => HashMap::new(),
+        };
+        for (name, value) in self.values.iter() {
+            snapshot.insert(name.clone(), value.as_ref().clone());
         }
+        snapshot
     }
 
+    pub fn depth(&self) -> usize {
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().depth() + 1,
+            None => 1,
+        }
+    }
+}
+
+impl From<HashMap<String, Value>> for Environment {
+    fn from(values: HashMap<String, Value>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl From<Vec<(&str, Value)>> for Environment {
+    fn from(values: Vec<(&str, Value)>) -> Self {
+        values
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+}
+
+// a global scope holding `values`
+impl FromIterator<(String, Value)> for Environment {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(values: I) -> Self {
+        let mut environment = Environment::default();
+        for (name, value) in values {
+            environment.define(&name, Rc::new(value));
+        }
+        environment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Environment;
+    use crate::{error::RloxError, interpreter::Value, scanner::{Scanner, Token}};
+    use std::{cell::RefCell, rc::Rc};
+
+    fn token(name: &str) -> Token {
+        Scanner::default().scan_tokens(name.to_string()).unwrap().remove(0)
+    }
+
+    // globals, then one scope per value, each defining `x` as that value
+    fn scopes(values: &[i64]) -> Rc<RefCell<Environment>> {
+        let mut environment = Rc::new(RefCell::new(Environment::default()));
+        for value in values {
+            let mut scope = Environment::new(environment);
+            scope.define("x", Rc::new(Value::Integer(*value)));
+            environment = Rc::new(RefCell::new(scope));
+        }
+        environment
+    }
+
+    #[test]
+    fn ancestor_walks_exactly_distance_scopes() {
+        let innermost = scopes(&[1, 2, 3]);
+        for (distance, value) in [(0, 3), (1, 2), (2, 1)] {
+            let ancestor = Environment::ancestor(&innermost, distance, &token("x")).unwrap();
+            assert_eq!(*ancestor.borrow().values["x"], Value::Integer(value));
+        }
+        let globals = Environment::ancestor(&innermost, 3, &token("x")).unwrap();
+        assert!(globals.borrow().enclosing.is_none());
+    }
+
+    #[test]
+    fn get_at_ignores_closer_shadowing_scopes() {
+        let innermost = scopes(&[1, 2, 3, 4]);
+        let innermost = innermost.borrow();
+        for (distance, value) in [(0, 4), (1, 3), (2, 2), (3, 1)] {
+            assert_eq!(*innermost.get_at(distance, &token("x")).unwrap(), Value::Integer(value));
+        }
+    }
+
+    #[test]
+    fn assign_at_only_changes_the_resolved_scope() {
+        let innermost = scopes(&[1, 2, 3]);
+        innermost
+            .borrow_mut()
+            .assign_at(&2, &token("x"), Rc::new(Value::Integer(10)))
+            .unwrap();
+        let innermost = innermost.borrow();
+        let values: Vec<_> = (0..3)
+            .map(|distance| innermost.get_at(distance, &token("x")).unwrap())
+            .collect();
+        assert_eq!(values, [3, 2, 10].map(|value| Rc::new(Value::Integer(value))));
+    }
+
+    #[test]
+    fn resolving_past_the_global_scope_is_an_error() {
+        let innermost = scopes(&[1]);
+        let past = |result: Result<_, RloxError>| match result {
+            Err(RloxError::RuntimeError { message, .. }) => message,
+            _ => panic!("expected a runtime error"),
+        };
+        let message = "Variable 'x' was resolved past the global scope.";
+        assert_eq!(past(Environment::ancestor(&innermost, 2, &token("x")).map(|_| ())), message);
+        assert_eq!(past(innermost.borrow().get_at(2, &token("x")).map(|_| ())), message);
+        let value = Rc::new(Value::Nil);
+        assert_eq!(past(innermost.borrow_mut().assign_at(&2, &token("x"), value)), message);
+        assert_eq!(past(Environment::default().get_at(1, &token("x")).map(|_| ())), message);
+    }
+
+
+    #[test]
+    fn snapshot_shows_inner_values_over_shadowed_ones() {
+        let globals = Environment::from(vec![("x", Value::Integer(1)), ("y", Value::Bool(true))]);
+        let mut inner = Environment::new(Rc::new(RefCell::new(globals)));
+        inner.define("x", Rc::new(Value::Integer(2)));
+        let snapshot = inner.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot["x"], Value::Integer(2));
+        assert_eq!(snapshot["y"], Value::Bool(true));
+        assert_eq!(inner.depth(), 2);
+        // reading leaves the scopes as they were
+        assert_eq!(*inner.enclosing.as_ref().unwrap().borrow().values["x"], Value::Integer(1));
+    }
+
+    #[test]
+    fn empty_environment_has_an_empty_snapshot() {
+        let environment = Environment::default();
+        assert!(environment.snapshot().is_empty());
+        assert_eq!(environment.depth(), 1);
+    }
 }
@@ -1,32 +1,215 @@
-use crate::{scanner::*, interpreter::{Value, Interpreter}};
+use crate::{scanner::*, interpreter::Value};
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
 
 #[derive(Debug)]
 pub enum RloxError {
-    ScanError { character: char, message: String },
-    UnterminatedStringError { token: String, message: String },
+    ScanError { line: usize, character: char, message: String },
+    UnterminatedStringError { line: usize, token: String, message: String },
     ParseError { current: usize, token: Token, message: String},
-    RuntimeError { lexeme: String, message: String},
+    ResolveError { line: usize, message: String },
+    // `span` is the byte range of the token in the source, when there is one
+    RuntimeError { lexeme: String, line: usize, message: String, span: Option<(usize, usize)> },
+    // raised by natives, which don't know where they were called from
+    NativeError { message: String },
+    AssertionFailed { line: usize, message: String },
+    // a value thrown by `throw`, caught as is by `catch`
+    UserError { line: usize, value: Value },
+    // raised by `panic(message)`, never caught, `line` is filled in by the call that raised it
+    Panic { line: Option<usize>, message: String },
+    // a step callback asked to stop before the statement on `line`
+    Interrupted { line: usize },
+    // a script that couldn't be read
+    FileError { path: String, message: String },
+    // an error raised while running the file `name`
+    InFile { name: Rc<str>, error: Box<RloxError> },
+    // an error raised by the code an `eval` call ran, positions in `error` point into `source`
+    InEval { source: Rc<str>, error: Box<RloxError> },
     Return(Value),
+    Break(Option<String>),
+    Continue,
     InterpreterError,
 }
 
 impl RloxError {
-    pub fn report(&self){
+    pub fn report(&self) {
+        eprintln!("{}", self)
+    }
+
+    // the line the error happened on, and the byte range of its token when known
+    fn position(&self) -> Option<(usize, Option<(usize, usize)>)> {
+        match self {
+            RloxError::ParseError { token, .. } => Some((token.line, Some((token.start_byte, token.end_byte)))),
+            RloxError::RuntimeError { line, span, .. } => Some((*line, *span)),
+            RloxError::ScanError { line, .. }
+            | RloxError::UnterminatedStringError { line, .. }
+            | RloxError::ResolveError { line, .. }
+            | RloxError::AssertionFailed { line, .. }
+            | RloxError::UserError { line, .. }
+            | RloxError::Panic { line: Some(line), .. }
+            | RloxError::Interrupted { line } => Some((*line, None)),
+            RloxError::InFile { error, .. } | RloxError::InEval { error, .. } => error.position(),
+            _ => None,
+        }
+    }
+
+    // the source the error's position points into, when it isn't the one the program was run from
+    fn own_source(&self) -> Option<&str> {
+        match self {
+            RloxError::InFile { error, .. } => error.own_source(),
+            RloxError::InEval { source, error } => error.own_source().or(Some(source)),
+            _ => None,
+        }
+    }
+
+    // the error without the `InFile` and `InEval` wrappers saying where it came from
+    pub fn inner(&self) -> &RloxError {
+        match self {
+            RloxError::InFile { error, .. } | RloxError::InEval { error, .. } => error.inner(),
+            error => error,
+        }
+    }
+
+    // the message followed by the offending line of `source`, with `^` under the token when its position is known
+    pub fn display_with_source(&self, source: &str) -> String {
+        let source = self.own_source().unwrap_or(source);
+        let Some((line, span)) = self.position() else {
+            return self.to_string();
+        };
+        let Some((start, end)) = span.filter(|(start, _)| source.is_char_boundary(*start)) else {
+            return match source.lines().nth(line.wrapping_sub(1)) {
+                Some(text) => format!("{}\n{} | {}", self, line, text),
+                None => self.to_string(),
+            };
+        };
+        // the end of the source has no width, point just past the last token instead
+        let start = if start == end { source[..start].trim_end().len() } else { start };
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..].find('\n').map_or(source.len(), |i| start + i);
+        let number = (source[..start].matches('\n').count() + 1).to_string();
+        let column = source[line_start..start].chars().count();
+        let width = source
+            .get(start..end.min(line_end))
+            .map_or(0, |lexeme| lexeme.chars().count())
+            .max(1);
+        format!(
+            "{}\n{} | {}\n{} | {}{}",
+            self,
+            number,
+            source[line_start..line_end].trim_end_matches('\r'),
+            " ".repeat(number.len()),
+            " ".repeat(column),
+            "^".repeat(width)
+        )
+    }
+
+    // like `Display`, with `[file.lox:12]` in place of `[line 12]` when `file` is given
+    fn fmt_in(&self, f: &mut Formatter<'_>, file: Option<&str>) -> std::fmt::Result {
+        let at = |line: &usize| match file {
+            Some(file) => format!("{}:{}", file, line),
+            None => format!("line {}", line),
+        };
         match &self {
-            RloxError::ScanError { character, message } => {
-               eprintln!("[line {}] Error {}", character, message)
+            RloxError::ScanError { line, character, message } => {
+               write!(f, "[{}] Error at '{}': {}", at(line), character, message)
+            }
+            RloxError::ParseError { token, message, .. } => {
+                let location = match file {
+                    Some(_) => at(&token.line),
+                    None => token.location(),
+                };
+                write!(f, "[{}] Error at {}: {}", location, token, message)
+            }
+            RloxError::ResolveError { line, message } => {
+                write!(f, "[{}] Error {}", at(line), message)
             }
-            RloxError::ParseError {current, token, message} => {
-                eprintln!("[position {}] Error {} lexeme {:?}",current,message, token.lexeme )
+            RloxError::InterpreterError => write!(f, "todo: implement interpreter error messages"),
+            RloxError::UnterminatedStringError { line, message, .. } => {
+               write!(f, "[{}] Error: {}", at(line), message)
             }
-            RloxError::InterpreterError => eprintln!("todo: implement interpreter error messages"),
-            RloxError::UnterminatedStringError { token, message } => {
-               eprintln!("[line {}] Error {}", token, message)
+            RloxError::RuntimeError { line, message, .. } =>
+               write!(f, "[{}] RuntimeError: {}", at(line), message),
+            RloxError::NativeError { message } => write!(f, "Error {}", message),
+            RloxError::UserError { line, value } => {
+                write!(f, "[{}] Uncaught error: {}", at(line), value)
             }
-            RloxError::RuntimeError { lexeme, message } =>
-               eprintln!("[token {}] Error {}", lexeme, message),
-            RloxError::Return(a) => eprintln!("{}", Interpreter::stringify(a)),
+            RloxError::AssertionFailed { line, message } => {
+                write!(f, "[{}] Error {}", at(line), message)
+            }
+            RloxError::Panic { line: Some(line), message } => {
+                write!(f, "[{}] Panic: {}", at(line), message)
+            }
+            RloxError::Panic { line: None, message } => write!(f, "Panic: {}", message),
+            RloxError::Interrupted { line } => write!(f, "[{}] Execution interrupted.", at(line)),
+            RloxError::FileError { path, message } => write!(f, "Could not read {}: {}", path, message),
+            RloxError::InFile { name, error } => error.fmt_in(f, Some(name)),
+            RloxError::InEval { error, .. } => error.fmt_in(f, Some("<eval>")),
+            RloxError::Return(a) => write!(f, "{}", a),
+            RloxError::Break(_) => write!(f, "Error 'break' outside of a loop."),
+            RloxError::Continue => write!(f, "Error 'continue' outside of a loop."),
 
         }
     }
 }
+
+impl Display for RloxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_in(f, None)
+    }
+}
+
+// problems that don't stop the program from running
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    UnusedVariable { name: String, line: usize },
+    UnreachableCode { line: usize },
+}
+
+impl Warning {
+    pub fn report(&self) {
+        match &self {
+            Warning::UnusedVariable { name, line } => {
+                eprintln!("[line {}] Warning: unused variable '{}'.", line, name)
+            }
+            Warning::UnreachableCode { line } => {
+                eprintln!("[line {}] Warning: unreachable code.", line)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_interpreter::TestInterpreter;
+
+    fn shown(source: &str) -> String {
+        TestInterpreter::new().run(source).unwrap_err().display_with_source(source)
+    }
+
+    #[test]
+    fn points_at_the_offending_token() {
+        assert_eq!(
+            shown("var a = 1;\nprint a + nil;"),
+            "[line 2] RuntimeError: Operands must be two numbers or two strings.\n2 | print a + nil;\n  |         ^"
+        );
+        assert_eq!(
+            shown("print 1"),
+            "[line 1] Error at Eof: Expect ';' after value.\n1 | print 1\n  |        ^"
+        );
+    }
+
+    #[test]
+    fn points_into_the_evaluated_source() {
+        assert_eq!(
+            shown("var greeting = \"hello there\"; print eval(\"nil + 1\");"),
+            "[<eval>:1] RuntimeError: Operands must be two numbers or two strings.\n1 | nil + 1\n  |     ^"
+        );
+    }
+
+    #[test]
+    fn errors_from_eval_can_be_caught() {
+        let mut lox = TestInterpreter::new();
+        lox.run("try { eval(\"nil + 1\"); } catch (e) { print e; }").unwrap();
+        assert_eq!(lox.printed_lines(), ["Operands must be two numbers or two strings."]);
+    }
+}
@@ -0,0 +1,136 @@
+// C API for embedding rlox, declared in `include/rlox.h`.
+//
+// A `Lox` handle keeps its globals between runs. The strings returned by
+// `rlox_get_output` and `rlox_get_error` belong to the handle and stay valid
+// until the next `rlox_run` or `rlox_free` on it.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::ptr;
+use std::rc::Rc;
+
+use crate::error::RloxError;
+use crate::interpreter::Interpreter;
+use crate::optimizer::fold_constants;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+
+pub struct Lox {
+    interpreter: Interpreter,
+    buffer: Rc<RefCell<Vec<u8>>>,
+    output: CString,
+    error: Option<CString>,
+}
+
+impl Lox {
+    fn new() -> Self {
+        let buffer = Rc::new(RefCell::new(vec![]));
+        Self {
+            interpreter: Interpreter::new().with_output(buffer.clone()),
+            buffer,
+            output: CString::default(),
+            error: None,
+        }
+    }
+
+    fn run(&mut self, source: &str) -> Result<(), String> {
+        let mut parser = Parser::from_source(source).map_err(|errors| {
+            errors
+                .iter()
+                .map(RloxError::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })?;
+        let mut statements = parser.parse().map_err(|err| err.to_string())?;
+        fold_constants(&mut statements);
+        Resolver::new(&mut self.interpreter)
+            .resolve(&statements)
+            .map_err(|err| err.to_string())?;
+        self.interpreter
+            .interpret(&statements)
+            .map_err(|err| err.to_string())
+    }
+}
+
+// C strings end at the first NUL, so any inside the text are dropped
+fn c_string(text: String) -> CString {
+    CString::new(text.replace('\0', "")).expect("NUL bytes were removed")
+}
+
+/// Creates an interpreter, to be released with `rlox_free`.
+#[no_mangle]
+pub extern "C" fn rlox_new() -> *mut Lox {
+    Box::into_raw(Box::new(Lox::new()))
+}
+
+/// Releases an interpreter. NULL is ignored.
+///
+/// # Safety
+///
+/// `lox` must be NULL or come from `rlox_new`, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_free(lox: *mut Lox) {
+    if !lox.is_null() {
+        drop(Box::from_raw(lox));
+    }
+}
+
+/// Runs `source_len` bytes of UTF-8 source, returning 0 on success and 1 on
+/// any error, whose message `rlox_get_error` then gives.
+///
+/// # Safety
+///
+/// `lox` must come from `rlox_new`, and `source` must point to `source_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_run(lox: *mut Lox, source: *const c_char, source_len: usize) -> i32 {
+    let Some(lox) = lox.as_mut() else {
+        return 1;
+    };
+    let result = if source.is_null() {
+        Err("Source is NULL.".to_string())
+    } else {
+        let source = std::slice::from_raw_parts(source.cast::<u8>(), source_len);
+        match std::str::from_utf8(source) {
+            Ok(source) => lox.run(source),
+            Err(_) => Err("Source is not valid UTF-8.".to_string()),
+        }
+    };
+    let output = std::mem::take(&mut *lox.buffer.borrow_mut());
+    lox.output = c_string(String::from_utf8_lossy(&output).into_owned());
+    match result {
+        Ok(()) => {
+            lox.error = None;
+            0
+        }
+        Err(message) => {
+            lox.error = Some(c_string(message));
+            1
+        }
+    }
+}
+
+/// What the last `rlox_run` printed, empty before the first run.
+///
+/// # Safety
+///
+/// `lox` must be NULL or come from `rlox_new`. NULL gives NULL.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_get_output(lox: *mut Lox) -> *const c_char {
+    match lox.as_ref() {
+        Some(lox) => lox.output.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// The error message of the last `rlox_run`, or NULL if it succeeded.
+///
+/// # Safety
+///
+/// `lox` must be NULL or come from `rlox_new`. NULL gives NULL.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_get_error(lox: *mut Lox) -> *const c_char {
+    match lox.as_ref().and_then(|lox| lox.error.as_ref()) {
+        Some(error) => error.as_ptr(),
+        None => ptr::null(),
+    }
+}
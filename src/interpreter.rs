@@ -1,44 +1,237 @@
+use crate::ast_printer::AstPrinter;
 use crate::callable::*;
 use crate::environment::*;
 use crate::error::RloxError;
 use crate::expr::Expr;
 use crate::scanner::*;
 use crate::stmt::*;
+#[cfg(not(target_arch = "wasm32"))]
+use corosensei::{stack::DefaultStack, CoroutineResult, ScopedCoroutine, Yielder};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::io::Write;
+use std::ops::ControlFlow;
+#[cfg(not(target_arch = "wasm32"))]
+use std::ptr::NonNull;
 use std::rc::Rc;
 
-#[derive(Debug, Clone)]
+pub type WatchCallback = dyn Fn(&Value, &Value);
+// called before each statement runs. `Break` pauses a program run with `Interpreter::start` until
+// the next `Execution::resume`; `interpret` has nowhere to pause to, so there it stops the program
+// with `RloxError::Interrupted` and the interpreter stays usable for the next run
+pub type StepCallback = dyn FnMut(&Stmt) -> ControlFlow<()>;
+// digits kept after the decimal point when printing a fractional number
+pub const DEFAULT_NUMBER_PRECISION: usize = 10;
+// longest string `*` will build, in bytes, anything longer is a runtime error instead of an allocation failure
+pub const MAX_STRING_LENGTH: usize = 1 << 28;
+// stack for a program run by `Interpreter::start`, only the pages it touches get allocated
+#[cfg(not(target_arch = "wasm32"))]
+const EXECUTION_STACK_SIZE: usize = 8 << 20;
+type DeferredStmt = (Rc<Stmt>, Rc<RefCell<Environment>>);
+
+#[derive(Clone)]
 pub struct Interpreter {
     globals: Rc<RefCell<Environment>>,
     environment: Rc<RefCell<Environment>>,
     locals: HashMap<usize, usize>,
+    ieee_division: bool,
+    watchpoints: HashMap<String, Rc<WatchCallback>>,
+    step_callback: Option<Rc<RefCell<StepCallback>>>,
+    // one frame per running function, each deferred statement with the scope it was deferred in
+    deferred: Vec<Vec<DeferredStmt>>,
+    output: Rc<RefCell<dyn Write>>,
+    trace: bool,
+    profiling: bool,
+    max_loop_iterations: Option<u64>,
+    number_precision: usize,
+    // how far apart two floats can be and still be `==`, exact when `None`
+    number_epsilon: Option<f64>,
+    // string literals and type names handed out so far, `None` when interning is off
+    strings: Option<HashSet<Rc<str>>>,
+    // source line -> how many statements starting on it were executed
+    profile_data: HashMap<usize, u64>,
+    #[cfg(not(target_arch = "wasm32"))]
+    suspend: Suspend,
+}
+
+// where a step callback's `Break` hands control back to, set while an `Execution` runs
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct Suspend(Option<NonNull<Yielder<(), ()>>>);
+
+// a clone isn't the interpreter the execution is running, so it can't pause it
+#[cfg(not(target_arch = "wasm32"))]
+impl Clone for Suspend {
+    fn clone(&self) -> Self {
+        Suspend(None)
+    }
+}
+
+// a program started with `Interpreter::start`, nothing runs until the first `resume`
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Execution<'a> {
+    coroutine: ScopedCoroutine<'a, (), (), Result<(), RloxError>, DefaultStack>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub enum Progress {
+    // the step callback returned `Break`, `resume` carries on from the statement it was given
+    Paused,
+    Finished(Result<(), RloxError>),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Execution<'_> {
+    // runs until the step callback asks for a pause or the program ends, panics if it already ended
+    pub fn resume(&mut self) -> Progress {
+        match self.coroutine.resume(()) {
+            CoroutineResult::Yield(()) => Progress::Paused,
+            CoroutineResult::Return(result) => Progress::Finished(result),
+        }
+    }
+}
+
+// puts the interpreter back however a started program ends, including a paused `Execution`
+// being dropped, which unwinds the program's stack
+#[cfg(not(target_arch = "wasm32"))]
+struct Running<'a> {
+    interpreter: &'a mut Interpreter,
+    environment: Rc<RefCell<Environment>>,
+    deferred: usize,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.interpreter.suspend = Suspend(None);
+        self.interpreter.environment = self.environment.clone();
+        self.interpreter.deferred.truncate(self.deferred);
+    }
 }
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
-    Str(String),
+    // shared, so copying a string value doesn't copy its text, see `Interpreter::intern`
+    Str(Rc<str>),
+    // literals without a fraction or exponent, mixing with a Number gives a Number
+    Integer(i64),
+    #[cfg_attr(feature = "serde", serde(with = "number_bits"))]
     Number(f64),
     Bool(bool),
+    // serialized by name only, see `func_by_name`
+    #[cfg_attr(feature = "serde", serde(with = "func_by_name"))]
     Func(Rc<dyn RloxCallable>),
+    // raw binary data, see the bytes natives
+    Bytes(Rc<Vec<u8>>),
     Nil,
 }
 
+// how `print` shows a value: numbers without a needless `.0`, strings bare, functions as `<fn name>`
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match &self {
             Value::Nil => write!(f, "nil"),
-            Value::Number(num) => {
-                if num.fract() == 0.0 {
-                    write!(f, "{:.0}", num)
-                } else {
-                    write!(f, "{}", num)
-                }
-            }
+            Value::Integer(num) => write!(f, "{}", num),
+            Value::Number(num) => write!(f, "{}", format_number(*num, DEFAULT_NUMBER_PRECISION)),
             Value::Bool(b) => write!(f, "{}", b),
             Value::Str(s) => write!(f, "{}", s),
             Value::Func(func) => write!(f, "{:?}", func),
+            Value::Bytes(bytes) => write!(f, "<{} bytes>", bytes.len()),
+        }
+    }
+}
+
+// whole numbers print without a decimal point, others with at most
+// `precision` decimals and no trailing zeros, so 0.1 + 0.2 prints as 0.3
+pub fn format_number(n: f64, precision: usize) -> String {
+    if n.is_nan() {
+        "nan".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else if n.fract() == 0.0 {
+        format!("{:.0}", n)
+    } else {
+        let fixed = format!("{:.*}", precision, n);
+        // with no decimals left there are no trailing zeros to drop
+        if fixed.contains('.') {
+            fixed.trim_end_matches('0').trim_end_matches('.').to_string()
+        } else {
+            fixed
+        }
+    }
+}
+
+impl Value {
+    // like `Display`, but strings are quoted, for echoing values in the REPL
+    pub fn to_debug_string(&self) -> String {
+        match self {
+            Value::Str(s) => format!("\"{}\"", s),
+            value => value.to_string(),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Str(_) => "string",
+            Value::Integer(_) | Value::Number(_) => "number",
+            Value::Bool(_) => "bool",
+            Value::Func(_) => "function",
+            Value::Bytes(_) => "bytes",
+            Value::Nil => "nil",
+        }
+    }
+}
+
+// a function can't be rebuilt from its name, so it comes back as a stand-in that fails when called
+#[cfg(feature = "serde")]
+mod func_by_name {
+    use super::*;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(func: &Rc<dyn RloxCallable>, serializer: S) -> Result<S::Ok, S::Error> {
+        func.name().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rc<dyn RloxCallable>, D::Error> {
+        let name = Option::<String>::deserialize(deserializer)?;
+        Ok(Rc::new(DeserializedFunction { name }))
+    }
+}
+
+// numbers are written as JSON numbers when the decimal reads back to the same bits, otherwise,
+// as for nan and inf, as the hex of `f64::to_bits`: `"0x7FF0000000000000"` for inf
+#[cfg(feature = "serde")]
+mod number_bits {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(number: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        let round_trips = number.is_finite()
+            && number.to_string().parse::<f64>().map(f64::to_bits) == Ok(number.to_bits());
+        if round_trips {
+            serializer.serialize_f64(*number)
+        } else {
+            serializer.serialize_str(&format!("0x{:016X}", number.to_bits()))
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        Decimal(f64),
+        Bits(String),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        match Number::deserialize(deserializer)? {
+            Number::Decimal(number) => Ok(number),
+            Number::Bits(bits) => bits
+                .strip_prefix("0x")
+                .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+                .map(f64::from_bits)
+                .ok_or_else(|| D::Error::custom(format!("invalid number bits {:?}", bits))),
         }
     }
 }
@@ -49,76 +242,315 @@ impl PartialEq for Value {
             (Value::Bool(a), Value::Bool(b)) => a == b,
             (Value::Nil, Value::Nil) => true,
             (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::Integer(a), Value::Number(b)) | (Value::Number(b), Value::Integer(a)) => {
+                *a as f64 == *b
+            }
             (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => a == b,
+            // a function only equals itself
+            (Value::Func(a), Value::Func(b)) => std::ptr::addr_eq(Rc::as_ptr(a), Rc::as_ptr(b)),
             _ => false,
         }
     }
 }
 
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Interpreter {
     pub fn new() -> Self {
-        let globals = Rc::new(RefCell::new(Environment::default()));
+        Self::new_with_globals(Environment::default())
+    }
+
+    // uses `globals` as the global scope, with the natives defined on top of it
+    pub fn new_with_globals(globals: Environment) -> Self {
+        let globals = Rc::new(RefCell::new(globals));
         globals
             .borrow_mut()
             .define("clock", Rc::new(Value::Func(Rc::new(Clock {}))));
-        
+        globals
+            .borrow_mut()
+            .define("eval", Rc::new(Value::Func(Rc::new(Eval {}))));
+        globals
+            .borrow_mut()
+            .define("panic", Rc::new(Value::Func(Rc::new(Panic {}))));
+        globals.borrow_mut().define(
+            "format",
+            Rc::new(Value::Func(Rc::new(Format { escape_html: false }))),
+        );
+        globals
+            .borrow_mut()
+            .define("min", Rc::new(Value::Func(Rc::new(Extreme { max: false }))));
+        globals
+            .borrow_mut()
+            .define("max", Rc::new(Value::Func(Rc::new(Extreme { max: true }))));
+        globals.borrow_mut().define(
+            "formatHtml",
+            Rc::new(Value::Func(Rc::new(Format { escape_html: true }))),
+        );
+        for native in [
+            BytesNative::ReadFile,
+            BytesNative::WriteFile,
+            BytesNative::Len,
+            BytesNative::Get,
+            BytesNative::ToString,
+            BytesNative::FromString,
+        ] {
+            globals
+                .borrow_mut()
+                .define(native.name(), Rc::new(Value::Func(Rc::new(native))));
+        }
+
         Self {
             globals: globals.clone(),
             environment: globals,
             locals: HashMap::new(),
+            ieee_division: false,
+            watchpoints: HashMap::new(),
+            step_callback: None,
+            deferred: vec![],
+            output: Rc::new(RefCell::new(std::io::stdout())),
+            trace: false,
+            profiling: false,
+            max_loop_iterations: None,
+            number_precision: DEFAULT_NUMBER_PRECISION,
+            number_epsilon: None,
+            strings: Some(HashSet::new()),
+            profile_data: HashMap::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            suspend: Suspend::default(),
         }
     }
-    pub fn interpret(&mut self, statements: Vec<Stmt>) -> Result<(), RloxError> {
+
+    // sends `print` and trace output to `output` instead of stdout
+    pub fn with_output(mut self, output: Rc<RefCell<dyn Write>>) -> Self {
+        self.output = output;
+        self
+    }
+
+    // writes each statement with its line before running it
+    pub fn with_trace(mut self, enabled: bool) -> Self {
+        self.trace = enabled;
+        self
+    }
+
+    // makes a loop that runs its body more than `limit` times a runtime error, unlimited by default
+    pub fn with_max_loop_iterations(mut self, limit: u64) -> Self {
+        self.max_loop_iterations = Some(limit);
+        self
+    }
+
+    // how many decimals `print` keeps for fractional numbers, see `format_number`
+    pub fn with_number_precision(mut self, digits: u32) -> Self {
+        self.number_precision = digits as usize;
+        self
+    }
+
+    // makes `==`, `!=` and switch cases on floats allow a difference of up to `epsilon`, so `0.1 + 0.2 == 0.3`
+    pub fn with_number_epsilon(mut self, epsilon: f64) -> Self {
+        self.number_epsilon = Some(epsilon);
+        self
+    }
+
+    // shares one allocation between equal string literals, on by default
+    pub fn with_string_interning(mut self, enabled: bool) -> Self {
+        self.strings = enabled.then(HashSet::new);
+        self
+    }
+
+    // runs `callback` before every statement, see `StepCallback`
+    pub fn with_step_callback(
+        mut self,
+        callback: impl FnMut(&Stmt) -> ControlFlow<()> + 'static,
+    ) -> Self {
+        self.step_callback = Some(Rc::new(RefCell::new(callback)));
+        self
+    }
+
+    // counts statement executions per source line, see `profile_report`
+    pub fn with_profiling(mut self, enabled: bool) -> Self {
+        self.profiling = enabled;
+        self
+    }
+
+    pub fn profile_report(&self) -> HashMap<usize, u64> {
+        self.profile_data.clone()
+    }
+
+    pub fn reset_profile(&mut self) {
+        self.profile_data.clear();
+    }
+
+    // division by zero yields inf/NaN instead of a runtime error
+    pub fn with_ieee_division(mut self, enabled: bool) -> Self {
+        self.ieee_division = enabled;
+        self
+    }
+
+    // the callback gets the old and the new value after each assignment to the global `name`,
+    // the old value of a global declared without a value is nil
+    pub fn watch(&mut self, name: &str, callback: Box<WatchCallback>) {
+        self.watchpoints.insert(name.to_string(), Rc::from(callback));
+    }
+
+    pub fn clear_watchpoint(&mut self, name: &str) {
+        self.watchpoints.remove(name);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.globals
+            .borrow()
+            .values
+            .get(name)
+            .map(|value| value.as_ref().clone())
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.globals.borrow_mut().define(name, Rc::new(value));
+    }
+
+    // defines a global native function backed by `function`
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: Arity,
+        function: impl Fn(&mut Interpreter, &[Rc<Value>]) -> Result<Rc<Value>, RloxError> + 'static,
+    ) {
+        let native = Native {
+            name: name.to_string(),
+            arity,
+            function: Rc::new(function),
+        };
+        self.set_global(name, Value::Func(Rc::new(native)));
+    }
+
+    // calls `callee` like a call expression would, for natives calling back into Lox
+    pub fn call_function(&mut self, callee: &Value, args: &[Rc<Value>]) -> Result<Rc<Value>, RloxError> {
+        let Value::Func(function) = callee else {
+            return Err(RloxError::NativeError {
+                message: "Can only call functions and classes.".to_string(),
+            });
+        };
+        if !function.arity().accepts(args.len()) {
+            return Err(RloxError::NativeError {
+                message: format!(
+                    "Expected {} arguments but got {}.",
+                    function.arity(),
+                    args.len()
+                ),
+            });
+        }
+        function.call(self, args)
+    }
+    // like `interpret`, but run in steps: a step callback returning `Break` pauses the program
+    // and `Execution::resume` picks it up again where it stopped
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start<'a>(&'a mut self, statements: &'a [Stmt]) -> Execution<'a> {
+        let stack = DefaultStack::new(EXECUTION_STACK_SIZE).expect("failed to allocate the execution stack");
+        let coroutine = ScopedCoroutine::with_stack(stack, move |yielder: &Yielder<(), ()>, ()| {
+            let running = Running {
+                environment: self.environment.clone(),
+                deferred: self.deferred.len(),
+                interpreter: self,
+            };
+            running.interpreter.suspend = Suspend(Some(NonNull::from(yielder)));
+            running.interpreter.interpret(statements)
+        });
+        Execution { coroutine }
+    }
+
+    // hands control back to whoever resumed the execution, false when not running under `start`
+    #[cfg(not(target_arch = "wasm32"))]
+    fn pause(&self) -> bool {
+        let Some(yielder) = self.suspend.0 else {
+            return false;
+        };
+        // SAFETY: `suspend` is only set while the execution's coroutine runs this interpreter,
+        // and the yielder lives on that coroutine's stack for as long as it runs
+        unsafe { yielder.as_ref() }.suspend(());
+        true
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn pause(&self) -> bool {
+        false
+    }
+
+    // borrows the program so callers can keep it, statements are never cloned
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), RloxError> {
         for statement in statements {
-            self.execute(&statement)?
+            self.execute(statement)?
         }
         Ok(())
     }
     fn evaluate(&mut self, expr: &Expr) -> Result<Rc<Value>, RloxError> {
         match expr {
             Expr::Nil => Ok(Rc::new(Value::Nil)),
+            Expr::Integer(n) => Ok(Rc::new(Value::Integer(*n))),
             Expr::Number(n) => Ok(Rc::new(Value::Number(*n))),
-            Expr::String(s) => Ok(Rc::new(Value::Str(s.to_string()))),
+            Expr::String(s) => Ok(Rc::new(Value::Str(self.intern(s)))),
             Expr::Boolean(b) => Ok(Rc::new(Value::Bool(*b))),
             Expr::Grouping { expression } => self.evaluate(expression),
+            Expr::Block { statements } => {
+                let block = Rc::new(RefCell::new(Environment::new(self.environment.clone())));
+                let previous = std::mem::replace(&mut self.environment, block);
+                let result = self.eval_statements(statements);
+                self.environment = previous;
+                result
+            }
             Expr::Binary {
                 left,
                 operator,
                 right,
-            } => self.binary_expr(left, &operator.token_type, right),
-            Expr::Unary { operator, right } => self.unary_expr(&operator.token_type, right),
+            } => self.binary_expr(left, operator, right),
+            Expr::Unary { operator, right } => self.unary_expr(operator, right),
             Expr::Logical {
                 left,
                 operator,
                 right,
             } => self.logical_expr(left, &operator.token_type, right),
-            Expr::Variable { id, name } => {
-                let depth = self.locals.get(id).copied();
-                if let Some(depth) = depth {
-                    self.environment.borrow().get_at(depth, name)
-                } else {
-                    self.globals.borrow().get_at(0, name)
-                }
-            }
+            Expr::Variable { id, name } => self.look_up_variable(*id, name),
             Expr::Assign { id, name, value } => {
                 let value = self.evaluate(value)?;
-                let depth = self.locals.get(id).copied();
-
-                if let Some(depth) = depth {
-                    self.environment
-                        .borrow_mut()
-                        .assign_at(&depth, name, value.clone())?;
-                } else {
-                    self.globals
-                        .borrow_mut()
-                        .assign_at(&0, name, value.clone())?;
-                }
+                self.assign_variable(*id, name, value.clone())?;
                 Ok(value)
             }
-            Expr::Call { callee, arguments } => self.call_expr(callee, arguments),
+            Expr::Postfix { id, name, operator } => Ok(self.step_variable(*id, name, operator)?.0),
+            Expr::Prefix { id, name, operator } => Ok(self.step_variable(*id, name, operator)?.1),
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => self.call_expr(callee, paren, arguments),
         }
     }
 
+    // adds or subtracts one for `++` and `--`, giving back the old and the new value
+    fn step_variable(
+        &mut self,
+        id: usize,
+        name: &Token,
+        operator: &Token,
+    ) -> Result<(Rc<Value>, Rc<Value>), RloxError> {
+        let previous = self.look_up_variable(id, name)?;
+        let step = if operator.token_type == TokenType::PlusPlus { 1 } else { -1 };
+        let value = match *previous {
+            Value::Integer(n) => n
+                .checked_add(step)
+                .map(Value::Integer)
+                .ok_or_else(|| Self::runtime_error(operator, "Integer overflow."))?,
+            Value::Number(n) => Value::Number(n + step as f64),
+            _ => return Err(Self::runtime_error(operator, "Operand must be a number.")),
+        };
+        let value = Rc::new(value);
+        self.assign_variable(id, name, value.clone())?;
+        Ok((previous, value))
+    }
+
     fn logical_expr(
         &mut self,
         left: &Expr,
@@ -141,16 +573,37 @@ impl Interpreter {
     fn binary_expr(
         &mut self,
         left: &Expr,
-        token_type: &TokenType,
+        operator: &Token,
         right: &Expr,
     ) -> Result<Rc<Value>, RloxError> {
         let left = &*self.evaluate(left)?;
         let right = &*self.evaluate(right)?;
 
-        match (left, token_type, right) {
+        if let (Value::Integer(l), Value::Integer(r)) = (left, right) {
+            if let Some(result) = self.integer_binary(*l, operator, *r) {
+                return result;
+            }
+        }
+        // mixed integer and float arithmetic happens in floating point
+        let (left, right) = match (left, right) {
+            (Value::Integer(l), Value::Number(r)) => (&Value::Number(*l as f64), &Value::Number(*r)),
+            (Value::Number(l), Value::Integer(r)) => (&Value::Number(*l), &Value::Number(*r as f64)),
+            _ => (left, right),
+        };
+
+        match (left, &operator.token_type, right) {
             (Value::Number(l), TokenType::Star, Value::Number(r)) => {
                 Ok(Rc::new(Value::Number(l * r)))
             }
+            (Value::Number(l), TokenType::Slash, Value::Number(r)) => {
+                if *r == 0.0 && !self.ieee_division {
+                    if *l == 0.0 {
+                        return Err(Self::runtime_error(operator, "Indeterminate form 0/0."));
+                    }
+                    return Err(Self::runtime_error(operator, "Division by zero."));
+                }
+                Ok(Rc::new(Value::Number(l / r)))
+            }
             (Value::Number(l), TokenType::Minus, Value::Number(r)) => {
                 Ok(Rc::new(Value::Number(l - r)))
             }
@@ -158,61 +611,188 @@ impl Interpreter {
                 Ok(Rc::new(Value::Number(l + r)))
             }
             (Value::Number(l), TokenType::Greater, Value::Number(r)) => {
-                Ok(Rc::new(Value::Bool(l.gt(&r))))
+                Ok(Rc::new(Value::Bool(l.gt(r))))
             }
             (Value::Number(l), TokenType::GreaterEqual, Value::Number(r)) => {
-                Ok(Rc::new(Value::Bool(l.ge(&r))))
+                Ok(Rc::new(Value::Bool(l.ge(r))))
             }
             (Value::Number(l), TokenType::Less, Value::Number(r)) => {
-                Ok(Rc::new(Value::Bool(l.lt(&r))))
+                Ok(Rc::new(Value::Bool(l.lt(r))))
             }
             (Value::Number(l), TokenType::LessEqual, Value::Number(r)) => {
-                Ok(Rc::new(Value::Bool(l.le(&r))))
+                Ok(Rc::new(Value::Bool(l.le(r))))
             }
             (Value::Str(l), TokenType::Plus, Value::Str(r)) => {
-                Ok(Rc::new(Value::Str(l.clone() + &r)))
+                Ok(Rc::new(Value::Str([l.as_ref(), r.as_ref()].concat().into())))
             }
-            (Value::Number(l), TokenType::EqualEqual, Value::Number(r)) => {
-                Ok(Rc::new(Value::Bool(l.eq(&r))))
+            // strings order lexicographically by code point
+            (Value::Str(l), TokenType::Greater, Value::Str(r)) => Ok(Rc::new(Value::Bool(l > r))),
+            (Value::Str(l), TokenType::GreaterEqual, Value::Str(r)) => {
+                Ok(Rc::new(Value::Bool(l >= r)))
             }
-            (Value::Number(l), TokenType::BangEqual, Value::Number(r)) => {
-                Ok(Rc::new(Value::Bool(!l.eq(&r))))
+            (Value::Str(l), TokenType::Less, Value::Str(r)) => Ok(Rc::new(Value::Bool(l < r))),
+            (Value::Str(l), TokenType::LessEqual, Value::Str(r)) => {
+                Ok(Rc::new(Value::Bool(l <= r)))
             }
-            (Value::Bool(l), TokenType::EqualEqual, Value::Bool(r)) => {
-                Ok(Rc::new(Value::Bool(l.eq(&r))))
+            (Value::Str(s), TokenType::Star, Value::Integer(n))
+            | (Value::Integer(n), TokenType::Star, Value::Str(s)) => {
+                if *n < 0 {
+                    return Err(Self::runtime_error(
+                        operator,
+                        "Can only repeat a string a non-negative whole number of times.",
+                    ));
+                }
+                Self::repeat_string(operator, s, *n as usize)
             }
-            (Value::Bool(l), TokenType::BangEqual, Value::Bool(r)) => {
-                Ok(Rc::new(Value::Bool(!l.eq(&r))))
+            (Value::Str(s), TokenType::Star, Value::Number(n))
+            | (Value::Number(n), TokenType::Star, Value::Str(s)) => {
+                if *n < 0.0 || n.fract() != 0.0 {
+                    return Err(Self::runtime_error(
+                        operator,
+                        "Can only repeat a string a non-negative whole number of times.",
+                    ));
+                }
+                Self::repeat_string(operator, s, *n as usize)
+            }
+            (l, TokenType::EqualEqual, r) => Ok(Rc::new(Value::Bool(self.is_equal(l, r)))),
+            (l, TokenType::BangEqual, r) => Ok(Rc::new(Value::Bool(!self.is_equal(l, r)))),
+            (_, TokenType::Plus, _) => Err(Self::runtime_error(
+                operator,
+                "Operands must be two numbers or two strings.",
+            )),
+            (
+                _,
+                TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual,
+                _,
+            ) => Err(Self::runtime_error(
+                operator,
+                "Operands must be two numbers or two strings.",
+            )),
+            (_, TokenType::Star | TokenType::Slash | TokenType::Minus, _) => {
+                Err(Self::runtime_error(operator, "Operands must be numbers."))
             }
             _ => Err(RloxError::InterpreterError),
         }
     }
-    fn unary_expr(&mut self, token_type: &TokenType, expr: &Expr) -> Result<Rc<Value>, RloxError> {
+    // arithmetic and comparisons between two integers, None for other operators
+    fn integer_binary(
+        &self,
+        l: i64,
+        operator: &Token,
+        r: i64,
+    ) -> Option<Result<Rc<Value>, RloxError>> {
+        let overflow = || Self::runtime_error(operator, "Integer overflow.");
+        let value = match operator.token_type {
+            TokenType::Plus => l.checked_add(r).map(Value::Integer).ok_or_else(overflow),
+            TokenType::Minus => l.checked_sub(r).map(Value::Integer).ok_or_else(overflow),
+            TokenType::Star => l.checked_mul(r).map(Value::Integer).ok_or_else(overflow),
+            TokenType::Slash if r == 0 => {
+                if self.ieee_division {
+                    Ok(Value::Number(l as f64 / r as f64))
+                } else if l == 0 {
+                    Err(Self::runtime_error(operator, "Indeterminate form 0/0."))
+                } else {
+                    Err(Self::runtime_error(operator, "Division by zero."))
+                }
+            }
+            // truncates toward zero
+            TokenType::Slash => l.checked_div(r).map(Value::Integer).ok_or_else(overflow),
+            TokenType::Greater => Ok(Value::Bool(l > r)),
+            TokenType::GreaterEqual => Ok(Value::Bool(l >= r)),
+            TokenType::Less => Ok(Value::Bool(l < r)),
+            TokenType::LessEqual => Ok(Value::Bool(l <= r)),
+            _ => return None,
+        };
+        Some(value.map(Rc::new))
+    }
+
+    fn unary_expr(&mut self, operator: &Token, expr: &Expr) -> Result<Rc<Value>, RloxError> {
         let right = self.evaluate(expr)?;
-        match token_type {
+        match operator.token_type {
             TokenType::Minus => match *right {
+                Value::Integer(n) => n
+                    .checked_neg()
+                    .map(|n| Rc::new(Value::Integer(n)))
+                    .ok_or_else(|| Self::runtime_error(operator, "Integer overflow.")),
                 Value::Number(n) => Ok(Rc::new(Value::Number(-n))),
-                _ => Err(RloxError::InterpreterError),
+                _ => Err(Self::runtime_error(operator, "Operand must be a number.")),
             },
             TokenType::Bang => Ok(Rc::new(Value::Bool(!self.is_truthy(&right)))),
+            TokenType::Typeof => Ok(Rc::new(Value::Str(self.intern(right.type_name())))),
             _ => Err(RloxError::InterpreterError),
         }
     }
+    fn look_up_variable(&self, id: usize, name: &Token) -> Result<Rc<Value>, RloxError> {
+        match self.locals.get(&id) {
+            Some(depth) => self.environment.borrow().get_at(*depth, name),
+            None => self.globals.borrow().get_at(0, name),
+        }
+    }
+
+    // assigns to a local or global, telling a watchpoint on the global about it
+    fn assign_variable(&mut self, id: usize, name: &Token, value: Rc<Value>) -> Result<(), RloxError> {
+        if let Some(depth) = self.locals.get(&id).copied() {
+            return self.environment.borrow_mut().assign_at(&depth, name, value);
+        }
+        // read the stored value directly, a `var x;` that was never assigned counts as nil
+        let previous = self.globals.borrow().values.get(&name.lexeme).cloned();
+        self.globals.borrow_mut().assign_at(&0, name, value.clone())?;
+        if let (Some(callback), Some(previous)) = (self.watchpoints.get(&name.lexeme), previous) {
+            callback(&previous, &value);
+        }
+        Ok(())
+    }
+    fn repeat_string(operator: &Token, s: &str, times: usize) -> Result<Rc<Value>, RloxError> {
+        match s.len().checked_mul(times) {
+            Some(length) if length <= MAX_STRING_LENGTH => Ok(Rc::new(Value::Str(s.repeat(times).into()))),
+            _ => Err(Self::runtime_error(operator, "Repeated string is too long.")),
+        }
+    }
+    fn runtime_error(token: &Token, message: &str) -> RloxError {
+        RloxError::RuntimeError {
+            lexeme: token.lexeme.clone(),
+            line: token.line,
+            message: message.to_string(),
+            span: Some((token.start_byte, token.end_byte)),
+        }
+    }
+    // `Value::eq`, unless an epsilon was set for comparing floats, an integer
+    // compared with a float counts as a float, two integers are always exact
+    fn is_equal(&self, left: &Value, right: &Value) -> bool {
+        let (l, r) = match (left, right) {
+            (Value::Number(l), Value::Number(r)) => (*l, *r),
+            (Value::Integer(l), Value::Number(r)) => (*l as f64, *r),
+            (Value::Number(l), Value::Integer(r)) => (*l, *r as f64),
+            _ => return left.eq(right),
+        };
+        match self.number_epsilon {
+            Some(epsilon) => l == r || (l - r).abs() <= epsilon,
+            None => l == r,
+        }
+    }
     // anything except null and false is true
     fn is_truthy(&self, right: &Value) -> bool {
-        match *right {
-            Value::Bool(false) | Value::Nil => false,
-            _ => true,
+        !matches!(*right, Value::Bool(false) | Value::Nil)
+    }
+
+    // the pooled copy of `text`, or a fresh one when interning is off
+    fn intern(&mut self, text: &str) -> Rc<str> {
+        let Some(strings) = &mut self.strings else {
+            return text.into();
+        };
+        if let Some(interned) = strings.get(text) {
+            return interned.clone();
         }
+        let interned: Rc<str> = text.into();
+        strings.insert(interned.clone());
+        interned
     }
 
-    pub fn stringify(value: &Value) -> String {
-        match &value {
-            Value::Str(s) => s.to_string(),
-            Value::Number(n) => n.to_string(),
-            Value::Bool(b) => b.to_string(),
-            Value::Nil => "nil".to_string(),
-            Value::Func(_) => "<func>".to_string(),
+    // like `Display`, but with this interpreter's number precision
+    fn to_output(&self, value: &Value) -> String {
+        match value {
+            Value::Number(n) => format_number(*n, self.number_precision),
+            _ => value.to_string(),
         }
     }
     pub fn add_scopes(&mut self, scopes: HashMap<usize, usize>) {
@@ -222,26 +802,69 @@ impl Interpreter {
     }
 
     fn execute(&mut self, statement: &Stmt) -> Result<(), RloxError> {
+        if self.profiling {
+            *self.profile_data.entry(statement.line()).or_insert(0) += 1;
+        }
+        if self.trace {
+            writeln!(
+                self.output.borrow_mut(),
+                "[line {}] {}",
+                statement.line(),
+                AstPrinter {}.print_stmt(statement)
+            )
+            .map_err(|_| RloxError::InterpreterError)?;
+        }
+        if let Some(callback) = &self.step_callback {
+            let step = (callback.borrow_mut())(statement);
+            if step.is_break() && !self.pause() {
+                return Err(RloxError::Interrupted {
+                    line: statement.line(),
+                });
+            }
+        }
         match statement {
-            Stmt::Print { expression } => {
-                println!("{:?}", self.evaluate(expression)?);
+            Stmt::Print { expression, .. } => {
+                let value = self.evaluate(expression)?;
+                writeln!(self.output.borrow_mut(), "{}", self.to_output(&value))
+                    .map_err(|_| RloxError::InterpreterError)?;
                 Ok(())
             }
-            Stmt::Expression { expression } => {
+            Stmt::Expression { expression, .. } => {
                 self.evaluate(expression)?;
                 Ok(())
             }
-            Stmt::Var { name, initializer } => {
-                let value = if let Some(expression) = initializer {
-                    self.evaluate(&expression)?
-                } else {
-                    Rc::new(Value::Nil)
+            Stmt::Assert {
+                keyword,
+                condition,
+                message,
+            } => {
+                let condition = self.evaluate(condition)?;
+                if self.is_truthy(&condition) {
+                    return Ok(());
+                }
+                let message = match message {
+                    Some(message) => {
+                        let message = self.evaluate(message)?;
+                        self.to_output(&message)
+                    }
+                    None => format!("Assertion failed at line {}.", keyword.line),
                 };
-
-                self.environment.borrow_mut().define(&name, value);
+                Err(RloxError::AssertionFailed {
+                    line: keyword.line,
+                    message,
+                })
+            }
+            Stmt::Var { name, initializer, .. } => {
+                match initializer {
+                    Some(expression) => {
+                        let value = self.evaluate(expression)?;
+                        self.environment.borrow_mut().define(name, value);
+                    }
+                    None => self.environment.borrow_mut().declare(name),
+                }
                 Ok(())
             }
-            Stmt::Block { statements } => self.execute_block(
+            Stmt::Block { statements, .. } => self.execute_block(
                 statements,
                 Rc::new(RefCell::new(Environment::new(self.environment.clone()))),
             ),
@@ -249,6 +872,7 @@ impl Interpreter {
                 condition,
                 then_branch,
                 else_branch,
+                ..
             } => {
                 let condition = self.evaluate(condition)?;
                 if self.is_truthy(&condition) {
@@ -259,28 +883,162 @@ impl Interpreter {
                     Ok(())
                 }
             }
-            Stmt::While { condition, body } => {
-                let mut evaluated_condition = self.evaluate(&condition)?;
+            Stmt::While {
+                condition,
+                body,
+                else_branch,
+                line,
+            } => {
+                let mut iterations = 0;
+                let mut evaluated_condition = self.evaluate(condition)?;
                 while self.is_truthy(&evaluated_condition) {
-                    self.execute(body)?;
-                    evaluated_condition = self.evaluate(&condition)?;
+                    self.count_iteration(&mut iterations, *line)?;
+                    match self.execute(body) {
+                        Ok(()) | Err(RloxError::Continue) => {}
+                        Err(RloxError::Break(None)) => return Ok(()),
+                        Err(err) => return Err(err),
+                    }
+                    evaluated_condition = self.evaluate(condition)?;
+                }
+                match else_branch {
+                    Some(else_branch) => self.execute(else_branch),
+                    None => Ok(()),
+                }
+            }
+            Stmt::DoWhile {
+                body,
+                condition,
+                line,
+            } => {
+                let mut iterations = 0;
+                loop {
+                    self.count_iteration(&mut iterations, *line)?;
+                    match self.execute(body) {
+                        Ok(()) | Err(RloxError::Continue) => {}
+                        Err(RloxError::Break(None)) => break,
+                        Err(err) => return Err(err),
+                    }
+                    let evaluated_condition = self.evaluate(condition)?;
+                    if !self.is_truthy(&evaluated_condition) {
+                        break;
+                    }
                 }
                 Ok(())
             }
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                else_branch,
+                ..
+            } => {
+                // the initializer gets its own scope, like a block around the loop
+                let previous = self.environment.clone();
+                self.environment = Rc::new(RefCell::new(Environment::new(previous.clone())));
+                let result = self.execute_for(
+                    initializer,
+                    condition,
+                    increment,
+                    body,
+                    else_branch,
+                    statement.line(),
+                );
+                self.environment = previous;
+                result
+            }
+            Stmt::Switch {
+                subject,
+                cases,
+                default,
+                ..
+            } => {
+                let subject = self.evaluate(subject)?;
+                let mut matched = default.as_ref();
+                // a case whose guard fails doesn't match, later cases are still tried
+                for case in cases {
+                    let value = self.evaluate(&case.value)?;
+                    if !self.is_equal(&value, &subject) {
+                        continue;
+                    }
+                    let guard = match &case.guard {
+                        Some(guard) => {
+                            let guard = self.evaluate(guard)?;
+                            self.is_truthy(&guard)
+                        }
+                        None => true,
+                    };
+                    if guard {
+                        matched = Some(&case.body);
+                        break;
+                    }
+                }
+                let Some(body) = matched else {
+                    return Ok(());
+                };
+                match self.execute_block(
+                    body,
+                    Rc::new(RefCell::new(Environment::new(self.environment.clone()))),
+                ) {
+                    Err(RloxError::Break(None)) => Ok(()),
+                    result => result,
+                }
+            }
+            Stmt::Try {
+                body,
+                name,
+                handler,
+                ..
+            } => {
+                let result = self.execute_block(
+                    body,
+                    Rc::new(RefCell::new(Environment::new(self.environment.clone()))),
+                );
+                // runtime errors are caught as their message, `return`, `break` and the like pass through
+                let error = match result.as_ref().map_err(RloxError::inner) {
+                    Err(RloxError::RuntimeError { message, .. }) => Value::Str(message.as_str().into()),
+                    Err(RloxError::UserError { value, .. }) => value.clone(),
+                    _ => return result,
+                };
+                let mut environment = Environment::new(self.environment.clone());
+                environment.define(&name.lexeme, Rc::new(error));
+                self.execute_block(handler, Rc::new(RefCell::new(environment)))
+            }
+            Stmt::Throw { keyword, value } => Err(RloxError::UserError {
+                line: keyword.line,
+                value: self.evaluate(value)?.as_ref().clone(),
+            }),
+            Stmt::Defer { body, .. } => match self.deferred.last_mut() {
+                Some(frame) => {
+                    frame.push((body.clone(), self.environment.clone()));
+                    Ok(())
+                }
+                None => Err(RloxError::InterpreterError),
+            },
+            Stmt::Break { label, .. } => Err(RloxError::Break(
+                label.as_ref().map(|label| label.lexeme.clone()),
+            )),
+            Stmt::Labeled { label, body } => match self.execute(body) {
+                Err(RloxError::Break(Some(target))) if target == label.lexeme => Ok(()),
+                result => result,
+            },
+            Stmt::Continue { .. } => Err(RloxError::Continue),
             Stmt::Function {
                 name,
                 parameters,
                 body,
+                ..
             } => {
                 let function = Rc::new(Value::Func(Rc::new(RloxFunction::new(
+                    name.clone(),
                     parameters.clone(),
                     body.clone(),
                     self.environment.clone(),
                 ))));
-                self.environment.borrow_mut().define(&name, function);
+                self.environment.borrow_mut().define(name, function);
                 Ok(())
             }
-            Stmt::Return { value } => {
+            Stmt::Return { value, .. } => {
                 let value = if let Some(value) = value {
                     self.evaluate(value)?
                 } else {
@@ -291,6 +1049,100 @@ impl Interpreter {
         }
     }
 
+    // runs `statements` in the global scope and returns the value of a trailing expression statement
+    pub fn eval(&mut self, statements: &[Stmt]) -> Result<Rc<Value>, RloxError> {
+        let previous = std::mem::replace(&mut self.environment, self.globals.clone());
+        let result = self.eval_statements(statements);
+        self.environment = previous;
+        result
+    }
+
+    fn eval_statements(&mut self, statements: &[Stmt]) -> Result<Rc<Value>, RloxError> {
+        let Some((last, rest)) = statements.split_last() else {
+            return Ok(Rc::new(Value::Nil));
+        };
+        for statement in rest {
+            self.execute(statement)?;
+        }
+        if let Stmt::Expression { expression, .. } = last {
+            self.evaluate(expression)
+        } else {
+            self.execute(last)?;
+            Ok(Rc::new(Value::Nil))
+        }
+    }
+
+    // runs a function body, then its deferred statements in reverse order however the body exited
+    pub fn execute_function_body(
+        &mut self,
+        statements: &Vec<Stmt>,
+        new_env: Rc<RefCell<Environment>>,
+    ) -> Result<(), RloxError> {
+        self.deferred.push(vec![]);
+        let mut result = self.execute_block(statements, new_env);
+        let frame = self.deferred.pop().unwrap_or_default();
+        for (statement, environment) in frame.into_iter().rev() {
+            let previous = std::mem::replace(&mut self.environment, environment);
+            let deferred_result = self.execute(&statement);
+            self.environment = previous;
+            if let (Err(err), Ok(_) | Err(RloxError::Return(_))) = (deferred_result, &result) {
+                result = Err(err);
+            }
+        }
+        result
+    }
+
+    fn execute_for(
+        &mut self,
+        initializer: &Option<Box<Stmt>>,
+        condition: &Option<Expr>,
+        increment: &Option<Expr>,
+        body: &Stmt,
+        else_branch: &Option<Box<Stmt>>,
+        line: usize,
+    ) -> Result<(), RloxError> {
+        if let Some(initializer) = initializer {
+            self.execute(initializer)?;
+        }
+        let mut iterations = 0;
+        loop {
+            if let Some(condition) = condition {
+                let evaluated_condition = self.evaluate(condition)?;
+                if !self.is_truthy(&evaluated_condition) {
+                    break;
+                }
+            }
+            self.count_iteration(&mut iterations, line)?;
+            match self.execute(body) {
+                // the increment still runs after `continue`
+                Ok(()) | Err(RloxError::Continue) => {}
+                Err(RloxError::Break(None)) => return Ok(()),
+                Err(err) => return Err(err),
+            }
+            if let Some(increment) = increment {
+                self.evaluate(increment)?;
+            }
+        }
+        match else_branch {
+            Some(else_branch) => self.execute(else_branch),
+            None => Ok(()),
+        }
+    }
+
+    // each loop counts its own iterations, starting from zero every time the loop statement runs
+    fn count_iteration(&self, iterations: &mut u64, line: usize) -> Result<(), RloxError> {
+        *iterations += 1;
+        match self.max_loop_iterations {
+            Some(limit) if *iterations > limit => Err(RloxError::RuntimeError {
+                lexeme: "loop".to_string(),
+                line,
+                message: format!("Loop exceeded {} iterations.", limit),
+                span: None,
+            }),
+            _ => Ok(()),
+        }
+    }
+
     pub fn execute_block(
         &mut self,
         statements: &Vec<Stmt>,
@@ -300,16 +1152,20 @@ impl Interpreter {
         self.environment = new_env;
 
         for statement in statements {
-            self.execute(statement).map_err(|err| {
+            self.execute(statement).inspect_err(|_| {
                 self.environment = previous.clone();
-                err
             })?;
         }
         self.environment = previous;
         Ok(())
     }
 
-    fn call_expr(&mut self, callee: &Expr, arguments: &Vec<Expr>) -> Result<Rc<Value>, RloxError> {
+    fn call_expr(
+        &mut self,
+        callee: &Expr,
+        paren: &Token,
+        arguments: &Vec<Expr>,
+    ) -> Result<Rc<Value>, RloxError> {
         let callee = self.evaluate(callee)?;
 
         let mut args: Vec<Rc<Value>> = vec![];
@@ -318,13 +1174,668 @@ impl Interpreter {
             args.push(self.evaluate(arg)?);
         }
 
-        if let Value::Func(function) = callee.as_ref() {
-            if !arguments.len().eq(&function.arity()) {
-                return Err(RloxError::InterpreterError);
+        self.call_function(&callee, &args).map_err(|err| match err {
+            RloxError::NativeError { message } => Self::runtime_error(paren, &message),
+            RloxError::Panic { line: None, message } => RloxError::Panic {
+                line: Some(paren.line),
+                message,
+            },
+            err => err,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_number, Interpreter, Progress, Value};
+    use crate::callable::Arity;
+    use std::cell::RefCell;
+    use std::ops::ControlFlow;
+    use std::rc::Rc;
+    use crate::error::RloxError;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::stmt::Stmt;
+    use crate::test_interpreter::TestInterpreter;
+
+    fn runtime_error(source: &str) -> String {
+        match TestInterpreter::new().run(source) {
+            Err(RloxError::RuntimeError { message, .. }) => message,
+            result => panic!("expected a runtime error, got {:?}", result),
+        }
+    }
+
+    fn printed(source: &str) -> Vec<String> {
+        let mut lox = TestInterpreter::new();
+        lox.run(source).unwrap();
+        lox.printed_lines().to_vec()
+    }
+
+    #[test]
+    fn postfix_increment_gives_back_the_old_value() {
+        assert_eq!(printed("var x = 0.1; print x++ == 0.1; print x;"), ["true", "1.1"]);
+        assert_eq!(printed("var x = 1; print x--; print x;"), ["1", "0"]);
+        assert_eq!(printed("fun f() { var i = 5; print i++; print i; } f();"), ["5", "6"]);
+    }
+
+    #[test]
+    fn postfix_increment_needs_a_number() {
+        assert_eq!(runtime_error(r#"var s = "a"; s++;"#), "Operand must be a number.");
+        assert_eq!(runtime_error(r#"var s = "a"; s--;"#), "Operand must be a number.");
+        assert_eq!(runtime_error("var n = 9223372036854775807; n++;"), "Integer overflow.");
+    }
+
+    #[test]
+    fn prefix_increment_gives_back_the_new_value() {
+        assert_eq!(printed("var x = 1; print ++x; print x; print --x; print x;"), ["2", "2", "1", "1"]);
+        assert_eq!(printed("fun f() { var i = 0.5; print ++i; print i; } f();"), ["1.5", "1.5"]);
+    }
+
+    #[test]
+    fn prefix_increment_needs_a_number() {
+        assert_eq!(runtime_error(r#"var s = "a"; ++s;"#), "Operand must be a number.");
+        assert_eq!(runtime_error(r#"var s = "a"; --s;"#), "Operand must be a number.");
+        assert_eq!(runtime_error("var b = true; --b;"), "Operand must be a number.");
+        assert_eq!(runtime_error("var n = -9223372036854775807 - 1; --n;"), "Integer overflow.");
+    }
+
+    #[test]
+    fn watchpoints_see_assignments_to_uninitialized_globals() {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let mut lox = TestInterpreter::new();
+        let log = seen.clone();
+        lox.watch("w", Box::new(move |old, new| log.borrow_mut().push(format!("{} -> {}", old, new))));
+        lox.run("var w; w = 1; w = 2;").unwrap();
+        assert_eq!(*seen.borrow(), ["nil -> 1", "1 -> 2"]);
+    }
+
+    #[test]
+    fn switch_guards_pick_between_equal_cases() {
+        let source = r#"
+            fun sign(n) {
+                switch (n) {
+                    case n if n > 0: return "positive";
+                    case n if n < 0: return "negative";
+                    case n: return "zero";
+                }
             }
-            return function.call(self, &args);
-        } else {
-            return Err(RloxError::InterpreterError);
+            print sign(3); print sign(-2); print sign(0);
+            switch (1) { case 1 if false: print "guarded"; default: print "default"; }
+        "#;
+        assert_eq!(printed(source), ["positive", "negative", "zero", "default"]);
+    }
+
+    #[test]
+    fn float_equality_is_exact_by_default() {
+        assert_eq!(printed("print 0.1 + 0.2 == 0.3; print 0.1 + 0.2 != 0.3;"), ["false", "true"]);
+    }
+
+    #[test]
+    fn float_equality_can_allow_an_epsilon() {
+        let mut lox = TestInterpreter::from_interpreter(Interpreter::new().with_number_epsilon(1e-9));
+        lox.run(
+            "print 0.1 + 0.2 == 0.3; print 0.1 + 0.2 != 0.3; print 1.0 == 1.1;
+            print 3 == 3.0000000001; print 1 == 2;
+            switch (0.1 + 0.2) { case 0.3: print \"matched\"; default: print \"default\"; }",
+        )
+        .unwrap();
+        assert_eq!(lox.printed_lines(), ["true", "false", "false", "true", "false", "matched"]);
+    }
+
+    #[test]
+    fn step_callback_can_interrupt_the_program() {
+        let steps = Rc::new(RefCell::new(0));
+        let counter = steps.clone();
+        let interpreter = Interpreter::new().with_step_callback(move |_| {
+            *counter.borrow_mut() += 1;
+            if *counter.borrow() > 5 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        let mut lox = TestInterpreter::from_interpreter(interpreter);
+        let result = lox.run("var i = 0; while (i < 10) { print i; i = i + 1; }");
+        assert!(matches!(result, Err(RloxError::Interrupted { line: 1 })));
+        assert_eq!(lox.printed_lines(), ["0"]);
+        assert_eq!(*steps.borrow(), 6);
+    }
+
+    // resolves `source` for `interpreter`, ready for `start`
+    fn prepared(interpreter: &mut Interpreter, source: &str) -> Vec<Stmt> {
+        let statements = Parser::from_source(source).unwrap().parse().unwrap();
+        Resolver::new(interpreter).resolve(&statements).unwrap();
+        statements
+    }
+
+    fn written(output: &Rc<RefCell<Vec<u8>>>) -> String {
+        String::from_utf8(std::mem::take(&mut *output.borrow_mut())).unwrap()
+    }
+
+    #[test]
+    fn step_callback_can_pause_and_resume_the_program() {
+        let steps = Rc::new(RefCell::new(0));
+        let counter = steps.clone();
+        let output = Rc::new(RefCell::new(vec![]));
+        let mut interpreter = Interpreter::new()
+            .with_output(output.clone())
+            .with_step_callback(move |_| {
+                *counter.borrow_mut() += 1;
+                if *counter.borrow() == 5 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            });
+        let statements = prepared(&mut interpreter, "var i = 0; while (i < 3) { print i; i = i + 1; } print \"done\";");
+        let mut execution = interpreter.start(&statements);
+        assert!(matches!(execution.resume(), Progress::Paused));
+        assert_eq!(written(&output), "0\n");
+        assert_eq!(*steps.borrow(), 5);
+        assert!(matches!(execution.resume(), Progress::Finished(Ok(()))));
+        assert_eq!(written(&output), "1\n2\ndone\n");
+    }
+
+    #[test]
+    fn programs_pause_inside_functions_and_native_callbacks() {
+        let output = Rc::new(RefCell::new(vec![]));
+        let mut interpreter = Interpreter::new()
+            .with_output(output.clone())
+            .with_step_callback(|statement| match statement {
+                Stmt::Return { .. } => ControlFlow::Break(()),
+                _ => ControlFlow::Continue(()),
+            });
+        interpreter.define_native("applyTwice", Arity::Fixed(2), |interpreter, args| {
+            let once = interpreter.call_function(&args[0], &args[1..])?;
+            interpreter.call_function(&args[0], &[once])
+        });
+        let statements = prepared(
+            &mut interpreter,
+            "fun double(x) { print x; return x * 2; } print 1 + applyTwice(double, 3);",
+        );
+        let mut execution = interpreter.start(&statements);
+        let mut pauses = vec![];
+        let result = loop {
+            match execution.resume() {
+                Progress::Paused => pauses.push(written(&output)),
+                Progress::Finished(result) => break result,
+            }
+        };
+        assert!(result.is_ok());
+        assert_eq!(pauses, ["3\n", "6\n"]);
+        assert_eq!(written(&output), "13\n");
+    }
+
+    #[test]
+    fn dropping_a_paused_program_leaves_the_interpreter_usable() {
+        let output = Rc::new(RefCell::new(vec![]));
+        let mut interpreter = Interpreter::new()
+            .with_output(output.clone())
+            .with_step_callback(|statement| match statement {
+                Stmt::Print { .. } => ControlFlow::Break(()),
+                _ => ControlFlow::Continue(()),
+            });
+        let statements = prepared(&mut interpreter, "var g = 1; fun f() { var local = 2; { print local; } } f();");
+        let mut execution = interpreter.start(&statements);
+        assert!(matches!(execution.resume(), Progress::Paused));
+        drop(execution);
+        let mut lox = TestInterpreter::from_interpreter(interpreter.with_step_callback(|_| ControlFlow::Continue(())));
+        lox.run("print g; var local = 3; print local;").unwrap();
+        assert_eq!(lox.printed_lines(), ["1", "3"]);
+    }
+
+    #[test]
+    fn step_callback_sees_every_statement() {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let log = seen.clone();
+        let interpreter = Interpreter::new().with_step_callback(move |statement| {
+            let kind = format!("{:?}", statement);
+            log.borrow_mut().push(kind[..kind.find(' ').unwrap_or(kind.len())].to_string());
+            ControlFlow::Continue(())
+        });
+        let mut lox = TestInterpreter::from_interpreter(interpreter);
+        lox.run("fun f() { return 1; } if (f() == 1) print 2;").unwrap();
+        assert_eq!(*seen.borrow(), ["Function", "If", "Return", "Print"]);
+    }
+
+    #[test]
+    fn formats_numbers() {
+        assert_eq!(format_number(10.5, 0), "10");
+        assert_eq!(format_number(100.0, 0), "100");
+        assert_eq!(format_number(0.5, 0), "0");
+        assert_eq!(format_number(2.50, 3), "2.5");
+        assert_eq!(format_number(0.1 + 0.2, 10), "0.3");
+    }
+
+    #[test]
+    fn repeats_strings() {
+        let mut lox = TestInterpreter::new();
+        lox.run(r#"print "ab" * 3; print 2.0 * "c";"#).unwrap();
+        assert_eq!(lox.printed_lines(), ["ababab", "cc"]);
+    }
+
+    #[test]
+    fn repeating_a_string_too_often_is_a_runtime_error() {
+        assert_eq!(runtime_error(r#"print "ab" * 100000000000000;"#), "Repeated string is too long.");
+        assert_eq!(runtime_error(r#"print "ab" * 1e300;"#), "Repeated string is too long.");
+    }
+
+    #[test]
+    fn else_if_chain_runs_only_the_matching_branch() {
+        let source = r#"
+            var calls = 0;
+            fun check(wanted, n) { calls = calls + 1; return wanted == n; }
+            fun pick(n) {
+                calls = 0;
+                if (check(1, n)) print "one";
+                else if (check(2, n)) print "two";
+                else if (check(3, n)) print "three";
+                else if (check(4, n)) print "four";
+                else print "other";
+                print calls;
+            }
+            pick(1); pick(2); pick(3); pick(4); pick(5);
+        "#;
+        assert_eq!(
+            printed(source),
+            ["one", "1", "two", "2", "three", "3", "four", "4", "other", "4"]
+        );
+    }
+
+    #[test]
+    fn catch_binds_the_runtime_error_message() {
+        assert_eq!(
+            printed(r#"try { print 1; print nil + 1; print 2; } catch (e) { print e; } print "after";"#),
+            ["1", "Operands must be two numbers or two strings.", "after"]
+        );
+    }
+
+    #[test]
+    fn thrown_values_reach_catch_unchanged() {
+        let source = r#"
+            fun f() {}
+            var thrown = f;
+            try { throw thrown; } catch (e) { print e == f; }
+            try { throw 42; } catch (e) { print e + 1; }
+            try { throw "oops"; } catch (e) { print e; }
+        "#;
+        assert_eq!(printed(source), ["true", "43", "oops"]);
+    }
+
+    #[test]
+    fn nested_try_can_rethrow() {
+        let source = r#"
+            try {
+                try { throw "inner"; } catch (e) { print "inner caught " + e; throw e + " again"; }
+                print "unreachable";
+            } catch (e) { print "outer caught " + e; }
+            try {
+                try { nil(); } catch (e) { throw "wrapped"; }
+            } catch (e) { print e; }
+        "#;
+        assert_eq!(
+            printed(source),
+            ["inner caught inner", "outer caught inner again", "wrapped"]
+        );
+    }
+
+    #[test]
+    fn control_flow_passes_through_try() {
+        let source = r#"
+            fun f() { try { return "returned"; } catch (e) { return "caught"; } }
+            print f();
+            while (true) { try { break; } catch (e) { print "caught"; } }
+            print "done";
+        "#;
+        assert_eq!(printed(source), ["returned", "done"]);
+    }
+
+    #[test]
+    fn panics_assertions_and_interrupts_are_not_caught() {
+        let mut lox = TestInterpreter::new();
+        let result = lox.run(r#"try { panic("stop"); } catch (e) { print "caught"; }"#);
+        assert!(matches!(result, Err(RloxError::Panic { .. })));
+        let result = lox.run(r#"try { assert false, "nope"; } catch (e) { print "caught"; }"#);
+        assert!(matches!(result, Err(RloxError::AssertionFailed { .. })));
+        assert!(lox.printed_lines().is_empty());
+
+        // stops at the first print, which is inside the try
+        let interpreter = Interpreter::new().with_step_callback(|statement| match statement {
+            Stmt::Print { .. } => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        });
+        let mut lox = TestInterpreter::from_interpreter(interpreter);
+        let result = lox.run(r#"try { print 1; } catch (e) { print "caught"; }"#);
+        assert!(matches!(result, Err(RloxError::Interrupted { .. })));
+        assert!(lox.printed_lines().is_empty());
+    }
+
+    #[test]
+    fn uncaught_throw_reaches_the_caller() {
+        let mut lox = TestInterpreter::new();
+        let error = lox.run("fun f() { throw \"boom\"; }\nprint 1;\nf();").unwrap_err();
+        assert!(matches!(&error, RloxError::UserError { line: 1, value: Value::Str(s) } if &**s == "boom"));
+        assert_eq!(error.to_string(), "[line 1] Uncaught error: boom");
+        assert_eq!(lox.printed_lines(), ["1"]);
+    }
+
+    #[test]
+    fn integer_arithmetic_stays_integer() {
+        let mut lox = TestInterpreter::new();
+        lox.run("var a = 7; var b = 2; var sum = 1 + 1; var mixed = 1 + 1.0; var half = a / b; var floatHalf = a / 2.0;")
+            .unwrap();
+        assert!(matches!(lox.get_global("sum"), Some(Value::Integer(2))));
+        assert!(matches!(lox.get_global("mixed"), Some(Value::Number(n)) if n == 2.0));
+        assert!(matches!(lox.get_global("half"), Some(Value::Integer(3))));
+        assert!(matches!(lox.get_global("floatHalf"), Some(Value::Number(n)) if n == 3.5));
+        lox.run("print 7 / 2; print 7 / 2.0; print -7 / 2; print 10 / 3 == 3; print 10.0 / 3;").unwrap();
+        assert_eq!(lox.printed_lines(), ["3", "3.5", "-3", "true", "3.3333333333"]);
+    }
+
+    #[test]
+    fn integers_equal_the_same_float() {
+        assert_eq!(printed("print 1 == 1.0; print 1 != 1.0; print 2 == 2.5; print 1 < 1.5;"), ["true", "false", "false", "true"]);
+        assert_eq!(Value::Integer(3), Value::Number(3.0));
+    }
+
+    #[test]
+    fn integer_overflow_is_a_runtime_error() {
+        let max = "var max = 9223372036854775807;";
+        assert_eq!(runtime_error(&format!("{} print max + 1;", max)), "Integer overflow.");
+        assert_eq!(runtime_error(&format!("{} print max * 2;", max)), "Integer overflow.");
+        assert_eq!(runtime_error(&format!("{} print -max - 2;", max)), "Integer overflow.");
+        assert_eq!(runtime_error(&format!("{} print (-max - 1) / -1;", max)), "Integer overflow.");
+        assert_eq!(runtime_error("var zero = 0; print 1 / zero;"), "Division by zero.");
+    }
+
+    fn with_natives() -> TestInterpreter {
+        let mut lox = TestInterpreter::new();
+        // applyTwice(f, x) is f(f(x))
+        lox.define_native("applyTwice", Arity::Fixed(2), |interpreter, args| {
+            let once = interpreter.call_function(&args[0], &args[1..])?;
+            interpreter.call_function(&args[0], &[once])
+        });
+        // callNative(name, ...) calls the global native `name` with the remaining arguments
+        lox.define_native("callNative", Arity::AtLeast(1), |interpreter, args| {
+            let callee = interpreter.get_global(&args[0].to_string()).unwrap_or(Value::Nil);
+            interpreter.call_function(&callee, &args[1..])
+        });
+        lox
+    }
+
+    #[test]
+    fn natives_can_call_lox_functions() {
+        let mut lox = with_natives();
+        lox.run(
+            "fun double(x) { return x * 2; } print applyTwice(double, 5);
+            { var mark = \"!\"; fun exclaim(s) { return s + mark; } print applyTwice(exclaim, \"hi\"); }",
+        )
+        .unwrap();
+        assert_eq!(lox.printed_lines(), ["20", "hi!!"]);
+    }
+
+    #[test]
+    fn natives_can_call_natives() {
+        let mut lox = with_natives();
+        lox.run("print callNative(\"max\", 1, 7, 3); print callNative(\"callNative\", \"callNative\", \"min\", 4, 2);")
+            .unwrap();
+        assert_eq!(lox.printed_lines(), ["7", "2"]);
+        let error = lox.run("callNative(\"nothing\");").unwrap_err();
+        assert!(error.to_string().contains("Can only call functions and classes."));
+    }
+
+    #[test]
+    fn natives_can_reenter_the_interpreter_mid_call() {
+        let mut lox = with_natives();
+        // evalNow(source) runs eval from inside a native call
+        lox.define_native("evalNow", Arity::Fixed(1), |interpreter, args| {
+            let eval = interpreter.get_global("eval").unwrap_or(Value::Nil);
+            interpreter.call_function(&eval, args)
+        });
+        lox.run(
+            "var total = 1;
+            fun outer() {
+                var local = 10;
+                fun bump(n) { local = local + n; return evalNow(\"total = total * 2; total\") + local; }
+                print applyTwice(bump, 1);
+                print local;
+                print evalNow(\"total + 1\");
+            }
+            outer();
+            print total;",
+        )
+        .unwrap();
+        assert_eq!(lox.printed_lines(), ["28", "24", "5", "4"]);
+    }
+
+
+    #[test]
+    fn type_names_cover_every_value() {
+        let clock = TestInterpreter::new().get_global("clock").unwrap();
+        for (value, name) in [
+            (Value::Str("a".into()), "string"),
+            (Value::Integer(1), "number"),
+            (Value::Number(1.5), "number"),
+            (Value::Bool(true), "bool"),
+            (clock, "function"),
+            (Value::Bytes(Rc::new(vec![1])), "bytes"),
+            (Value::Nil, "nil"),
+        ] {
+            assert_eq!(value.type_name(), name);
         }
+        // the typeof native became the operator, called like a function it still works
+        assert_eq!(printed("print typeof(clock); print typeof(\"a\");"), ["function", "string"]);
+    }
+
+    #[test]
+    fn type_errors_name_the_expected_and_actual_types() {
+        assert_eq!(runtime_error("eval(1);"), "eval expects a string, got number.");
+        assert_eq!(runtime_error("format(nil);"), "format expects a string template, got nil.");
+        assert_eq!(runtime_error("max(1, true);"), "max expects numbers, got bool.");
+        assert_eq!(runtime_error("bytes_len(\"a\");"), "bytes_len expects bytes, got string.");
+    }
+
+
+    #[test]
+    fn arithmetic_on_non_numbers_is_a_runtime_error() {
+        assert_eq!(runtime_error("true + 1;"), "Operands must be two numbers or two strings.");
+        assert_eq!(runtime_error("nil * 2;"), "Operands must be numbers.");
+        assert_eq!(runtime_error("\"a\" - \"b\";"), "Operands must be numbers.");
+        let error = TestInterpreter::new().run("var a = 1;\n\nprint a / false;").unwrap_err();
+        assert_eq!(error.to_string(), "[line 3] RuntimeError: Operands must be numbers.");
+    }
+
+
+    #[test]
+    fn watchpoints_report_each_assignment_to_their_global() {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let mut lox = TestInterpreter::new();
+        let (log, globals) = (seen.clone(), lox.globals.clone());
+        lox.watch(
+            "x",
+            Box::new(move |old, new| {
+                // the global already holds the new value when the callback runs
+                let stored = globals.borrow().values["x"].clone();
+                log.borrow_mut().push(format!("{} -> {} ({})", old, new, stored));
+            }),
+        );
+        lox.run("var x = 1; var y = 1; x = 2; y = 3; x = x + 1; { var x = 10; x = 11; }").unwrap();
+        assert_eq!(*seen.borrow(), ["1 -> 2 (2)", "2 -> 3 (3)"]);
+        // an assignment that fails never happens, so it isn't reported
+        assert!(lox.run("x = nil + 1;").is_err());
+        assert_eq!(seen.borrow().len(), 2);
+
+        lox.clear_watchpoint("x");
+        lox.run("x = 4;").unwrap();
+        assert_eq!(seen.borrow().len(), 2);
+    }
+
+
+    #[test]
+    fn continue_in_a_for_loop_still_runs_the_increment() {
+        // the cap turns a skipped increment into an error instead of a hang
+        let mut lox = TestInterpreter::from_interpreter(Interpreter::new().with_max_loop_iterations(100));
+        lox.run("for (var i = 0; i < 5; i = i + 1) { if (i == 1 or i == 3) continue; print i; } print \"done\";")
+            .unwrap();
+        assert_eq!(lox.printed_lines(), ["0", "2", "4", "done"]);
+    }
+
+
+    #[test]
+    fn calling_a_non_function_names_the_line() {
+        for source in ["print 1;\nnil();", "print 1;\n(\"x\")();"] {
+            let error = TestInterpreter::new().run(source).unwrap_err();
+            assert_eq!(error.to_string(), "[line 2] RuntimeError: Can only call functions and classes.");
+        }
+    }
+
+
+    #[test]
+    fn trace_writes_each_statement_before_running_it() {
+        let mut lox = TestInterpreter::from_interpreter(Interpreter::new().with_trace(true));
+        lox.run("var a = 1;\nprint a + 1;").unwrap();
+        assert_eq!(lox.printed_lines(), ["[line 1] (var a 1)", "[line 2] (print (+ a 1))", "2"]);
+        let mut quiet = TestInterpreter::new();
+        quiet.run("var a = 1;\nprint a + 1;").unwrap();
+        assert_eq!(quiet.printed_lines(), ["2"]);
+    }
+
+
+    #[test]
+    fn functions_equal_only_themselves() {
+        let source = "
+            fun f() {}
+            var g = f;
+            fun make() { fun inner() {} return inner; }
+            var a = make();
+            var b = make();
+            var c = a;
+            print f == f; print f == g; print a == b; print a == c; print f == a; print clock == clock; print clock == f;";
+        assert_eq!(printed(source), ["true", "true", "false", "true", "false", "true", "false"]);
+    }
+
+
+    #[test]
+    fn switch_runs_default_only_when_no_case_matches() {
+        let source = "
+            fun name(n) {
+                switch (n) {
+                    default: print \"many\";
+                    case 0: print \"none\";
+                    case 1: print \"one\";
+                }
+            }
+            name(0); name(1); name(7);";
+        // cases don't fall through, into each other or the default
+        assert_eq!(printed(source), ["none", "one", "many"]);
+        assert_eq!(printed("switch (1) { case 2: print 2; }"), Vec::<String>::new());
+    }
+
+
+    #[test]
+    fn nan_and_infinity_compare_as_ieee_754_says() {
+        let source = "
+            var inf = 1e400;
+            var nan = inf - inf;
+            print inf; print -inf; print nan;
+            print nan == nan; print nan != nan; print nan < 1; print nan >= 1;
+            print inf == inf; print inf > 1e308; print -inf < -1e308; print inf == -inf;";
+        assert_eq!(
+            printed(source),
+            ["inf", "-inf", "nan", "false", "true", "false", "false", "true", "true", "true", "false"]
+        );
+        let mut lox = TestInterpreter::from_interpreter(Interpreter::new().with_ieee_division(true));
+        lox.run("print 1.0 / 0; print -1.0 / 0; print 0.0 / 0;").unwrap();
+        assert_eq!(lox.printed_lines(), ["inf", "-inf", "nan"]);
+    }
+
+
+    #[test]
+    fn loop_iteration_cap_stops_runaway_loops() {
+        let mut lox = TestInterpreter::from_interpreter(Interpreter::new().with_max_loop_iterations(10));
+        for _ in 0..2 {
+            let error = lox.run("var n = 0;\nwhile (true) { n = n + 1; }").unwrap_err();
+            assert_eq!(error.to_string(), "[line 2] RuntimeError: Loop exceeded 10 iterations.");
+            assert_eq!(lox.get_global("n"), Some(Value::Integer(10)));
+        }
+        // each loop gets its own count
+        lox.run("for (var i = 0; i < 8; i = i + 1) {} for (var i = 0; i < 8; i = i + 1) {} print \"ok\";")
+            .unwrap();
+        assert_eq!(lox.printed_lines(), ["ok"]);
+    }
+
+
+    #[test]
+    fn typeof_gives_the_type_name() {
+        let source = r#"
+            fun f() {}
+            print typeof 42 == "number"; print typeof 1.5 == "number"; print typeof "hi" == "string";
+            print typeof nil == "nil"; print typeof true == "bool"; print typeof f == "function";
+            print typeof clock; print typeof read_file_bytes; print typeof 1 + "!";"#;
+        assert_eq!(printed(source), ["true", "true", "true", "true", "true", "true", "function", "function", "number!"]);
+        assert_eq!(runtime_error("typeof 1 + 1;"), "Operands must be two numbers or two strings.");
+    }
+
+
+    #[test]
+    fn pipe_passes_the_left_value_to_the_right_function() {
+        let source = "
+            fun double(x) { return x * 2; }
+            fun inc(x) { return x + 1; }
+            fun show(x) { return \"<\" + format(\"{}\", x) + \">\"; }
+            print 5 |> double;
+            print 5 |> double |> inc |> show;
+            var step = inc;
+            print 1 |> step |> step;";
+        assert_eq!(printed(source), ["10", "<11>", "3"]);
+    }
+
+
+    #[test]
+    fn values_display_as_lox_prints_them() {
+        let mut lox = TestInterpreter::new();
+        lox.run("fun add(a, b) { return a + b; }").unwrap();
+        for (value, shown) in [
+            (Value::Nil, "nil"),
+            (Value::Integer(-3), "-3"),
+            (Value::Number(3.0), "3"),
+            (Value::Number(2.5), "2.5"),
+            (Value::Number(1.0 / 3.0), "0.3333333333"),
+            (Value::Bool(false), "false"),
+            (Value::Str("a \"b\"".into()), "a \"b\""),
+            (lox.get_global("add").unwrap(), "<fn add>"),
+            // natives don't give a name
+            (lox.get_global("clock").unwrap(), "<fn>"),
+            (Value::Bytes(Rc::new(vec![1, 2, 3])), "<3 bytes>"),
+        ] {
+            assert_eq!(value.to_string(), shown);
+        }
+    }
+
+
+    #[test]
+    fn interned_strings_equal_strings_built_at_runtime() {
+        let source = r#"
+            var literal = "ab";
+            var built = "a" + "b";
+            fun concat(x, y) { return x + y; }
+            print literal == built; print literal == concat("a", "b"); print typeof 1 == "num" + "ber";
+            print "ab" * 2 == "abab"; print literal != "a";"#;
+        for interning in [true, false] {
+            let mut lox = TestInterpreter::from_interpreter(Interpreter::new().with_string_interning(interning));
+            lox.run(source).unwrap();
+            assert_eq!(lox.printed_lines(), ["true", "true", "true", "true", "true"]);
+        }
+        // only interning shares the literal's allocation
+        let shared = |interning| {
+            let mut lox = TestInterpreter::from_interpreter(Interpreter::new().with_string_interning(interning));
+            lox.run("var a = \"x\"; var b = \"x\";").unwrap();
+            match (lox.get_global("a"), lox.get_global("b")) {
+                (Some(Value::Str(a)), Some(Value::Str(b))) => Rc::ptr_eq(&a, &b),
+                values => panic!("expected two strings, got {:?}", values),
+            }
+        };
+        assert!(shared(true));
+        assert!(!shared(false));
     }
 }
@@ -0,0 +1,21 @@
+#![allow(clippy::result_large_err)]
+
+pub mod ast_printer;
+pub mod callable;
+pub mod environment;
+pub mod error;
+pub mod expr;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod interpreter;
+pub mod lox;
+pub mod optimizer;
+pub mod parser;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod resolver;
+pub mod scanner;
+pub mod stmt;
+pub mod test_interpreter;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,182 @@
+use std::{fs::read_to_string, path::Path, rc::Rc};
+
+use crate::{
+    error::{RloxError, Warning},
+    interpreter::{Interpreter, Value},
+    optimizer::fold_constants,
+    parser::Parser,
+    resolver::Resolver,
+    scanner::Scanner,
+    stmt::Stmt,
+};
+
+// runs whole programs, files and REPL lines on one interpreter, keeping its globals between runs
+pub struct Lox {
+    interpreter: Interpreter,
+    // the script being run, tokens and errors are tagged with it
+    source_name: Option<Rc<str>>,
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lox {
+    pub fn new() -> Self {
+        Self::from_interpreter(Interpreter::new())
+    }
+
+    // for running programs on an interpreter set up with its builder methods
+    pub fn from_interpreter(interpreter: Interpreter) -> Self {
+        Self {
+            interpreter,
+            source_name: None,
+        }
+    }
+
+    // errors name the file, as in `[script.lox:12]`
+    pub fn run_file(&mut self, path: &Path) -> Result<(), Vec<RloxError>> {
+        let name: Rc<str> = path.display().to_string().into();
+        let file = read_to_string(path).map_err(|err| {
+            vec![RloxError::FileError {
+                path: name.to_string(),
+                message: err.to_string(),
+            }]
+        })?;
+        self.source_name = Some(name.clone());
+        let result = self.run(&file);
+        self.source_name = None;
+        result.map_err(|errors| {
+            errors
+                .into_iter()
+                .map(|error| RloxError::InFile {
+                    name: name.clone(),
+                    error: Box::new(error),
+                })
+                .collect()
+        })
+    }
+
+    // all scan errors are reported at once, later stages stop at the first error
+    pub fn run(&mut self, source: &str) -> Result<(), Vec<RloxError>> {
+        let statements = self.prepare(source, false)?;
+        self.interpreter.interpret(&statements).map_err(|err| vec![err])
+    }
+
+    // runs a REPL line, giving back the value to echo if it ends with an expression
+    pub fn run_line(&mut self, source: &str) -> Result<Option<Value>, Vec<RloxError>> {
+        let statements = self.prepare(source, true)?;
+        let value = self.interpreter.eval(&statements).map_err(|err| vec![err])?;
+        match statements.last() {
+            Some(Stmt::Expression { .. }) => Ok(Some(value.as_ref().clone())),
+            _ => Ok(None),
+        }
+    }
+
+    // parses `source` and writes its statements as JSON, each node an object with a "kind" field
+    // and its own fields, literals as `{"kind": "Integer", "value": 3}`
+    #[cfg(feature = "serde")]
+    pub fn compile_to_ast_json(source: &str) -> Result<String, RloxError> {
+        let mut parser = Parser::from_source(source).map_err(|mut errors| errors.remove(0))?;
+        let statements = parser.parse()?;
+        serde_json::to_string(&statements).map_err(|err| RloxError::NativeError {
+            message: format!("AST export failed: {}", err),
+        })
+    }
+
+    // parses and resolves `source`, reporting resolver warnings
+    fn prepare(&mut self, source: &str, trailing_expression: bool) -> Result<Vec<Stmt>, Vec<RloxError>> {
+        let mut scanner = match &self.source_name {
+            Some(name) => Scanner::default().with_source_name(name),
+            None => Scanner::default(),
+        };
+        let mut parser = Parser::new(scanner.scan_tokens(source.to_string())?);
+        let mut statements = if trailing_expression {
+            parser.parse_with_trailing_expression()
+        } else {
+            parser.parse()
+        }
+        .map_err(|err| vec![err])?;
+        fold_constants(&mut statements);
+
+        let mut resolver = Resolver::new(&mut self.interpreter);
+        resolver.resolve(&statements).map_err(|err| vec![err])?;
+        resolver.warnings().iter().for_each(Warning::report);
+        Ok(statements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lox;
+    use crate::interpreter::Interpreter;
+    use std::{cell::RefCell, env::temp_dir, fs::write, path::PathBuf, rc::Rc};
+
+    fn script(name: &str, source: &str) -> PathBuf {
+        let path = temp_dir().join(format!("rlox-{}-{}.lox", name, std::process::id()));
+        write(&path, source).unwrap();
+        path
+    }
+
+    #[test]
+    fn file_errors_name_the_file() {
+        let path = script("runtime-error", "var a = 1;\nprint nil + a;");
+        let errors = Lox::new().run_file(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            errors[0].to_string(),
+            format!("[{}:2] RuntimeError: Operands must be two numbers or two strings.", path.display())
+        );
+    }
+
+    #[test]
+    fn runs_a_valid_file() {
+        let path = script("valid", "var a = 1;\nprint a + 1;");
+        let output = Rc::new(RefCell::new(vec![]));
+        let mut lox = Lox::from_interpreter(Interpreter::new().with_output(output.clone()));
+        let result = lox.run_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_ok());
+        assert_eq!(*output.borrow(), b"2\n");
+        // the file name isn't kept for later runs
+        assert_eq!(
+            lox.run("print nil + 1;").unwrap_err()[0].to_string(),
+            "[line 1] RuntimeError: Operands must be two numbers or two strings."
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn ast_json_reads_back_as_the_same_statements() {
+        use crate::{parser::Parser, stmt::Stmt};
+
+        let source = "var x = 1 + 2;";
+        let json = Lox::compile_to_ast_json(source).unwrap();
+        assert!(json.contains(r#""kind":"Var""#), "{}", json);
+        assert!(json.contains(r#""kind":"Binary""#), "{}", json);
+        let statements: Vec<Stmt> = serde_json::from_str(&json).unwrap();
+        assert_eq!(statements, Parser::from_source(source).unwrap().parse().unwrap());
+    }
+
+    #[test]
+    fn repl_echo_quotes_strings_but_print_does_not() {
+        let output = Rc::new(RefCell::new(vec![]));
+        let mut lox = Lox::from_interpreter(Interpreter::new().with_output(output.clone()));
+        let echoed = |lox: &mut Lox, line: &str| lox.run_line(line).unwrap().map(|value| value.to_debug_string());
+        assert_eq!(echoed(&mut lox, "\"hi\""), Some("\"hi\"".to_string()));
+        assert_eq!(echoed(&mut lox, "print \"hi\";"), None);
+        assert_eq!(*output.borrow(), b"hi\n");
+        for (line, shown) in [("1 + 1", "2"), ("2.5", "2.5"), ("true", "true"), ("nil", "nil")] {
+            assert_eq!(echoed(&mut lox, line), Some(shown.to_string()));
+        }
+    }
+
+    #[test]
+    fn reports_a_missing_file() {
+        let path = temp_dir().join("rlox-no-such-file.lox");
+        let errors = Lox::new().run_file(&path).unwrap_err();
+        assert!(errors[0].to_string().contains("rlox-no-such-file.lox"));
+    }
+}
@@ -1,87 +1,54 @@
-mod environment;
-mod error;
-mod interpreter;
-mod parser;
-mod scanner;
-mod stmt;
-mod callable;
-mod resolver;
-mod expr;
+#![allow(clippy::result_large_err)]
 
-use error::RloxError;
 use std::{
     env::args,
     fs::read_to_string,
     io::{stdin, stdout, Write},
+    path::Path,
     process::exit,
 };
 
-use crate::interpreter::*;
-use crate::parser::*;
-use crate::resolver::*;
-
-struct Rlox {
-    interpreter: Interpreter,
-}
-
-impl Rlox {
-    pub fn new() -> Self {
-        Self {
-            interpreter: Interpreter::new(),
+use rlox::interpreter::Interpreter;
+use rlox::lox::Lox;
+
+fn run_prompt(lox: &mut Lox) -> std::io::Result<()> {
+    loop {
+        print!("> ");
+        stdout().flush()?;
+        let mut line = String::new();
+        stdin().read_line(&mut line)?;
+        if line.trim().is_empty() {
+            break;
         }
-    }
-    pub fn run_file(&mut self, path: &str) -> std::io::Result<()> {
-
-        let file = read_to_string(path)?;
-        match self.run(&file) {
-            Ok(_) => {}
-            Err(e) => {
-                e.report();
-            }
+        match lox.run_line(&line) {
+            Ok(Some(value)) => println!("{}", value.to_debug_string()),
+            Ok(None) => {}
+            Err(errors) => errors
+                .iter()
+                .for_each(|err| eprintln!("{}", err.display_with_source(&line))),
         }
-        Ok(())
-    }
-
-    pub fn run_prompt(&mut self) -> std::io::Result<()> {
-        Ok(loop {
-            print!("> ");
-            stdout().flush()?;
-            let mut line = String::new();
-            stdin().read_line(&mut line)?;
-            if line.trim().is_empty() {
-                break;
-            }
-            match self.run(&line) {
-                Ok(_) => {}
-                Err(e) => {
-                    e.report();
-                }
-            }
-        })
-    }
-    pub fn run(&mut self, source: &str) -> Result<(), RloxError> {
-        let scanner = scanner::Scanner::default().scan_tokens(source.to_string())?;
-        let mut parser = Parser {
-            tokens: scanner.to_vec(),
-            current: 0,
-        };
-        let statements = parser.parse()?;
-
-        let mut resolver = Resolver::new(self.interpreter.clone());
-        resolver.resolve(&statements)?;
-        resolver.interpreter.interpret(statements)
     }
+    Ok(())
 }
 
 fn main() -> std::io::Result<()> {
-    let mut rlox = Rlox::new();
-    let args: Vec<_> = args().collect();
+    let mut args: Vec<_> = args().collect();
+    let trace = args.iter().any(|arg| arg == "--trace");
+    args.retain(|arg| arg != "--trace");
+    let mut lox = Lox::from_interpreter(Interpreter::new().with_trace(trace));
     if args.len() > 2 {
-        println!("Usage: rlox [script]");
+        println!("Usage: rlox [--trace] [script]");
         exit(64);
     } else if args.len() == 2 {
-        rlox.run_file(&args[1])
+        let path = Path::new(&args[1]);
+        if let Err(errors) = lox.run_file(path) {
+            let source = read_to_string(path).unwrap_or_default();
+            errors
+                .iter()
+                .for_each(|err| eprintln!("{}", err.display_with_source(&source)));
+        }
+        Ok(())
     } else {
-        rlox.run_prompt()
+        run_prompt(&mut lox)
     }
 }
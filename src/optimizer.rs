@@ -0,0 +1,212 @@
+use std::rc::Rc;
+
+use crate::expr::Expr;
+use crate::scanner::{Token, TokenType};
+use crate::stmt::Stmt;
+
+// folds arithmetic on number literals, like `2 * 3 + 4` into `10`, run after parsing and before
+// resolving on the way to the interpreter, tools that want the tree as written skip it
+pub fn fold_constants(statements: &mut [Stmt]) {
+    statements.iter_mut().for_each(fold_statement);
+}
+
+fn fold_statement(statement: &mut Stmt) {
+    match statement {
+        Stmt::Assert { condition, message, .. } => {
+            fold_expression(condition);
+            message.iter_mut().for_each(fold_expression);
+        }
+        Stmt::Block { statements, .. } => fold_constants(statements),
+        Stmt::Break { .. } | Stmt::Continue { .. } => {}
+        // freshly parsed trees aren't shared yet, a shared one is left as it is
+        Stmt::Defer { body, .. } => {
+            if let Some(body) = Rc::get_mut(body) {
+                fold_statement(body);
+            }
+        }
+        Stmt::DoWhile { body, condition, .. } => {
+            fold_statement(body);
+            fold_expression(condition);
+        }
+        Stmt::Expression { expression, .. } | Stmt::Print { expression, .. } => {
+            fold_expression(expression)
+        }
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            fold_expression(condition);
+            fold_statement(then_branch);
+            else_branch.iter_mut().for_each(|branch| fold_statement(branch));
+        }
+        Stmt::Labeled { body, .. } => fold_statement(body),
+        Stmt::Function { body, .. } => {
+            if let Some(body) = Rc::get_mut(body) {
+                fold_constants(body);
+            }
+        }
+        Stmt::Return { value, .. } => value.iter_mut().for_each(fold_expression),
+        Stmt::Switch {
+            subject,
+            cases,
+            default,
+            ..
+        } => {
+            fold_expression(subject);
+            for case in cases {
+                fold_expression(&mut case.value);
+                case.guard.iter_mut().for_each(|guard| fold_expression(guard));
+                fold_constants(&mut case.body);
+            }
+            default.iter_mut().for_each(|default| fold_constants(default));
+        }
+        Stmt::Throw { value, .. } => fold_expression(value),
+        Stmt::Try { body, handler, .. } => {
+            fold_constants(body);
+            fold_constants(handler);
+        }
+        Stmt::Var { initializer, .. } => initializer.iter_mut().for_each(fold_expression),
+        Stmt::For {
+            initializer,
+            condition,
+            increment,
+            body,
+            else_branch,
+            ..
+        } => {
+            initializer.iter_mut().for_each(|initializer| fold_statement(initializer));
+            condition.iter_mut().for_each(fold_expression);
+            increment.iter_mut().for_each(fold_expression);
+            fold_statement(body);
+            else_branch.iter_mut().for_each(|branch| fold_statement(branch));
+        }
+        Stmt::While {
+            condition,
+            body,
+            else_branch,
+            ..
+        } => {
+            fold_expression(condition);
+            fold_statement(body);
+            else_branch.iter_mut().for_each(|branch| fold_statement(branch));
+        }
+    }
+}
+
+fn fold_expression(expr: &mut Expr) {
+    match expr {
+        Expr::Binary { left, operator, right } => {
+            fold_expression(left);
+            fold_expression(right);
+            if let Some(folded) = fold_binary(left, operator, right) {
+                *expr = folded;
+            }
+        }
+        Expr::Unary { operator, right } => {
+            fold_expression(right);
+            if operator.token_type == TokenType::Minus {
+                match constant(right) {
+                    Some(Expr::Integer(value)) if value.checked_neg().is_some() => {
+                        *expr = Expr::Integer(-value);
+                    }
+                    Some(Expr::Number(value)) => *expr = Expr::Number(-value),
+                    _ => {}
+                }
+            }
+        }
+        Expr::Block { statements } => fold_constants(statements),
+        Expr::Call { callee, arguments, .. } => {
+            fold_expression(callee);
+            arguments.iter_mut().for_each(fold_expression);
+        }
+        Expr::Assign { value, .. } => fold_expression(value),
+        Expr::Grouping { expression } => fold_expression(expression),
+        Expr::Logical { left, right, .. } => {
+            fold_expression(left);
+            fold_expression(right);
+        }
+        Expr::Postfix { .. }
+        | Expr::Prefix { .. }
+        | Expr::Variable { .. }
+        | Expr::Integer(_)
+        | Expr::Number(_)
+        | Expr::String(_)
+        | Expr::Boolean(_)
+        | Expr::Nil => {}
+    }
+}
+
+// the Integer or Number literal an expression is, looking through parentheses
+fn constant(expr: &Expr) -> Option<&Expr> {
+    match expr {
+        Expr::Integer(_) | Expr::Number(_) => Some(expr),
+        Expr::Grouping { expression } => constant(expression),
+        _ => None,
+    }
+}
+
+fn as_float(literal: &Expr) -> f64 {
+    match literal {
+        Expr::Integer(value) => *value as f64,
+        Expr::Number(value) => *value,
+        _ => unreachable!("only called on constants"),
+    }
+}
+
+// arithmetic on two number literals, `None` for anything that could error
+fn fold_binary(left: &Expr, operator: &Token, right: &Expr) -> Option<Expr> {
+    match (constant(left), constant(right)) {
+        (Some(Expr::Integer(l)), Some(Expr::Integer(r))) => match operator.token_type {
+            TokenType::Plus => l.checked_add(*r),
+            TokenType::Minus => l.checked_sub(*r),
+            TokenType::Star => l.checked_mul(*r),
+            TokenType::Slash => l.checked_div(*r),
+            _ => None,
+        }
+        .map(Expr::Integer),
+        (Some(l), Some(r)) => {
+            let (l, r) = (as_float(l), as_float(r));
+            match operator.token_type {
+                TokenType::Plus => Some(l + r),
+                TokenType::Minus => Some(l - r),
+                TokenType::Star => Some(l * r),
+                TokenType::Slash if r != 0.0 => Some(l / r),
+                _ => None,
+            }
+            .map(Expr::Number)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fold_constants;
+    use crate::ast_printer::AstPrinter;
+    use crate::parser::Parser;
+    use crate::stmt::Stmt;
+
+    fn folded(source: &str) -> String {
+        let mut statements = Parser::from_source(source).unwrap().parse().unwrap();
+        fold_constants(&mut statements);
+        let Stmt::Print { expression, .. } = &statements[0] else {
+            panic!("expected a print statement");
+        };
+        AstPrinter {}.print(expression)
+    }
+
+    #[test]
+    fn folds_literal_arithmetic() {
+        assert_eq!(folded("print 2 * 3 + 4;"), "10");
+        assert_eq!(folded("print -(1.5 * 2);"), "-3");
+        assert_eq!(folded("print 1 + 2 * x;"), "(+ 1 (* 2 x))");
+    }
+
+    #[test]
+    fn leaves_what_could_error_alone() {
+        assert_eq!(folded("print 1 / 0;"), "(/ 1 0)");
+        assert_eq!(folded("print 9223372036854775807 + 1;"), "(+ 9223372036854775807 1)");
+    }
+}
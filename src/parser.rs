@@ -9,20 +9,75 @@ use crate::expr::*;
 
 #[derive(Debug)]
 pub struct Parser {
-    pub tokens: Vec<Token>,
-    pub current: usize,
+    tokens: Vec<Token>,
+    current: usize,
+    trailing_expression: bool,
+    labels: Vec<String>,
+    // where the statement being parsed directly inside a block expression starts,
+    // an expression statement starting there may leave out its ';' before the closing '}'
+    block_value_start: Option<usize>,
+    // set while parsing an if's then branch outside any block, where an `else` belongs to the if
+    in_then_branch: bool,
+    // how many expressions are being parsed inside each other
+    depth: usize,
+    max_depth: usize,
 }
 
+// one `case value:` or `case value if guard:` arm of a switch with the statements under it
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CaseClause {
+    pub value: Expr,
+    pub guard: Option<Box<Expr>>,
+    pub body: Vec<Stmt>,
+}
 
 // TODO find a better solution
 static NEXT_EXPRESSION_ID: AtomicUsize = AtomicUsize::new(0);
 
+// deep enough for real code, shallow enough not to overflow the stack
+pub const DEFAULT_MAX_DEPTH: usize = 200;
+
 fn next_id() -> usize {
     NEXT_EXPRESSION_ID.fetch_add(1, Ordering::Relaxed)
 }
 
 
 impl Parser {
+    // `tokens` must end with an Eof token, as `Scanner::scan_tokens` returns them
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens,
+            current: 0,
+            trailing_expression: false,
+            block_value_start: None,
+            labels: vec![],
+            in_then_branch: false,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    // nesting expressions deeper than `limit` is a parse error instead of a stack overflow
+    pub fn with_max_depth(mut self, limit: usize) -> Self {
+        self.max_depth = limit;
+        self
+    }
+
+    // scans `source` and sets up a parser over its tokens
+    pub fn from_source(source: &str) -> Result<Self, Vec<RloxError>> {
+        let tokens = Scanner::default().scan_tokens(source.to_string())?;
+        Ok(Self::new(tokens))
+    }
+
+    // like `parse`, but the last expression statement may leave out its ';'
+    pub fn parse_with_trailing_expression(&mut self) -> Result<Vec<Stmt>, RloxError> {
+        self.trailing_expression = true;
+        let statements = self.parse();
+        self.trailing_expression = false;
+        statements
+    }
+
     pub fn parse(&mut self) -> Result<Vec<Stmt>, RloxError> {
         let mut statements: Vec<Stmt> = vec![];
         while !self.is_end() {
@@ -30,8 +85,42 @@ impl Parser {
         }
         Ok(statements)
     }
+
+    // parses a single expression with nothing after it, for input that isn't a program
+    pub fn parse_expression(&mut self) -> Result<Expr, RloxError> {
+        let expr = self.expression()?;
+        if !self.is_end() {
+            return Err(RloxError::ParseError {
+                current: self.current,
+                token: self.peek(),
+                message: "Expect end of expression.".to_string(),
+            });
+        }
+        Ok(expr)
+    }
+
     fn expression(&mut self) -> Result<Expr, RloxError> {
-        self.assignment()
+        self.nested(Self::assignment)
+    }
+
+    // runs `parse` one level deeper, every recursive expression rule goes through here
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Expr, RloxError>) -> Result<Expr, RloxError> {
+        self.descend("Expression too deeply nested.", parse)
+    }
+
+    // statements and blocks count against the same limit as expressions, they nest inside each other
+    fn descend<T>(&mut self, message: &str, parse: fn(&mut Self) -> Result<T, RloxError>) -> Result<T, RloxError> {
+        if self.depth >= self.max_depth {
+            return Err(RloxError::ParseError {
+                current: self.current,
+                token: self.peek(),
+                message: message.to_string(),
+            });
+        }
+        self.depth += 1;
+        let parsed = parse(self);
+        self.depth -= 1;
+        parsed
     }
 
     fn equality(&mut self) -> Result<Expr, RloxError> {
@@ -94,7 +183,7 @@ impl Parser {
 
     fn check(&self, token: TokenType) -> bool {
         if self.is_end() {
-            false;
+            return false;
         }
         self.peek().token_type == token
     }
@@ -103,6 +192,13 @@ impl Parser {
         self.tokens[self.current].clone()
     }
 
+    fn peek_next(&self) -> Token {
+        if self.is_end() {
+            return self.peek();
+        }
+        self.tokens[self.current + 1].clone()
+    }
+
     fn term(&mut self) -> Result<Expr, RloxError> {
         let mut expr = self.factor()?;
         while self.match_token(vec![TokenType::Minus, TokenType::Plus]) {
@@ -132,13 +228,18 @@ impl Parser {
     }
 
     fn unary(&mut self) -> Result<Expr, RloxError> {
-        if self.match_token(vec![TokenType::Bang, TokenType::Minus]) {
+        if self.match_token(vec![TokenType::PlusPlus, TokenType::MinusMinus]) {
             let operator = self.previous();
-            let right = self.unary()?;
-            Expr::Unary {
+            let target = self.nested(Self::unary)?;
+            return self.increment(target, operator);
+        }
+        if self.match_token(vec![TokenType::Bang, TokenType::Minus, TokenType::Typeof]) {
+            let operator = self.previous();
+            let right = self.nested(Self::unary)?;
+            return Ok(Expr::Unary {
                 right: Box::new(right),
                 operator,
-            };
+            });
         }
         self.call()
     }
@@ -163,15 +264,17 @@ impl Parser {
             return Ok(Expr::String(value));
         }
         if self.match_token(vec![TokenType::Number]) {
-            let value =if let Some(Literal::Number(val)) = self.previous().literal{
-                val
-            }else{
-                return Err(RloxError::InterpreterError);
+            return match self.previous().literal {
+                Some(Literal::Integer(val)) => Ok(Expr::Integer(val)),
+                Some(Literal::Number(val)) => Ok(Expr::Number(val)),
+                _ => Err(RloxError::InterpreterError),
             };
-            return Ok(Expr::Number(value));
         }
         if self.match_token(vec![TokenType::Identifier]) {
-            return Ok(Expr::Variable { id: next_id(), name: self.previous().lexeme });
+            return Ok(Expr::Variable { id: next_id(), name: self.previous() });
+        }
+        if self.match_token(vec![TokenType::LeftBrace]) {
+            return self.descend("Block too deeply nested.", Self::block_expression);
         }
         if self.match_token(vec![TokenType::LeftParen]) {
             let expr = self.expression()?;
@@ -184,17 +287,18 @@ impl Parser {
             });
         }
         Err(RloxError::ParseError {
-            token: self.tokens[self.current].clone(),
+            token: self.peek(),
             current: self.current,
-            message: "failed to parse".to_string(),
+            message: "Expect expression.".to_string(),
         })
-        } 
+    }
+
     fn consume(&mut self, token: TokenType, message: String) -> Result<Token, RloxError> {
         if self.check(token) {
             return Ok(self.advance());
         }
         Err(RloxError::ParseError {
-            token: self.tokens[self.current].clone(),
+            token: self.peek(),
             current: self.current,
             message,
         })
@@ -222,6 +326,10 @@ impl Parser {
     }
 
     fn statement(&mut self) -> Result<Stmt, RloxError> {
+        self.descend("Statement too deeply nested.", Self::unnested_statement)
+    }
+
+    fn unnested_statement(&mut self) -> Result<Stmt, RloxError> {
         if self.match_token(vec![TokenType::If]) {
             return self.if_statement();
         }
@@ -231,14 +339,52 @@ impl Parser {
         if self.match_token(vec![TokenType::While]) {
             return self.while_statement();
         }
+        if self.match_token(vec![TokenType::Do]) {
+            return self.do_while_statement();
+        }
+        if self.match_token(vec![TokenType::Loop]) {
+            return self.loop_statement();
+        }
+        if self.match_token(vec![TokenType::Switch]) {
+            return self.switch_statement();
+        }
+        if self.match_token(vec![TokenType::Try]) {
+            return self.try_statement();
+        }
+        if self.match_token(vec![TokenType::Throw]) {
+            let keyword = self.previous();
+            let value = self.expression()?;
+            self.consume(TokenType::Semicolon, "Expect ';' after thrown value.".to_string())?;
+            return Ok(Stmt::Throw { keyword, value });
+        }
         if self.match_token(vec![TokenType::Print]) {
             return self.print_statement();
         }
+        if self.match_token(vec![TokenType::Assert]) {
+            return self.assert_statement();
+        }
         if self.match_token(vec![TokenType::Return]) {
             return self.return_statement();
         }
+        if self.match_token(vec![TokenType::Break]) {
+            return self.break_statement();
+        }
+        if self.match_token(vec![TokenType::Defer]) {
+            let keyword = self.previous();
+            let body = Rc::new(self.statement()?);
+            return Ok(Stmt::Defer { keyword, body });
+        }
+        if self.check(TokenType::Identifier) && self.peek_next().token_type == TokenType::Colon {
+            return self.labeled_statement();
+        }
+        if self.match_token(vec![TokenType::Continue]) {
+            let keyword = self.previous();
+            self.consume(TokenType::Semicolon, "Expect ';' after 'continue'.".to_string())?;
+            return Ok(Stmt::Continue { keyword });
+        }
         if self.match_token(vec![TokenType::LeftBrace]) {
             return Ok(Stmt::Block {
+                line: self.previous().line,
                 statements: self.block()?,
             });
         }
@@ -246,18 +392,51 @@ impl Parser {
     }
 
     fn print_statement(&mut self) -> Result<Stmt, RloxError> {
+        let line = self.previous().line;
         let value = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value.".to_string())?;
-        return Ok(Stmt::Print { expression: value });
+        Ok(Stmt::Print {
+            line,
+            expression: value,
+        })
+    }
+
+    fn assert_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous();
+        let condition = self.expression()?;
+        let message = if self.match_token(vec![TokenType::Comma]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after assertion.".to_string())?;
+        Ok(Stmt::Assert {
+            keyword,
+            condition,
+            message,
+        })
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, RloxError> {
+        let start = self.current;
+        let line = self.peek().line;
         let value = self.expression()?;
+        let ends_block_value =
+            self.block_value_start == Some(start) && self.check(TokenType::RightBrace);
+        if (self.trailing_expression && self.is_end()) || ends_block_value {
+            return Ok(Stmt::Expression {
+                line,
+                expression: value,
+            });
+        }
         self.consume(
             TokenType::Semicolon,
             "Expect ';' after expression.".to_string(),
         )?;
-        return Ok(Stmt::Expression { expression: value });
+        Ok(Stmt::Expression {
+            line,
+            expression: value,
+        })
     }
 
     fn declaration(&mut self) -> Result<Stmt, RloxError> {
@@ -287,13 +466,14 @@ impl Parser {
         self.consume(TokenType::Semicolon, "Expect ';' after value.".to_string())?;
 
         Ok(Stmt::Var {
+            line: name.line,
             name: name.lexeme,
             initializer,
         })
     }
 
     fn assignment(&mut self) -> Result<Expr, RloxError> {
-        let expr = self.or()?;
+        let expr = self.pipe()?;
 
         if self.match_token(vec![TokenType::Equal]) {
             let equals = self.previous();
@@ -317,12 +497,46 @@ impl Parser {
         Ok(expr)
     }
 
+    // `{ ... }` in expression position, valued like `eval`: by its trailing expression statement, or nil
+    fn block_expression(&mut self) -> Result<Expr, RloxError> {
+        let enclosing = self.block_value_start.take();
+        let mut statements: Vec<Stmt> = vec![];
+        let result = loop {
+            if self.check(TokenType::RightBrace) || self.is_end() {
+                break Ok(());
+            }
+            self.block_value_start = Some(self.current);
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(err) => break Err(err),
+            }
+        };
+        self.block_value_start = enclosing;
+        result?;
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.".to_string())?;
+
+        Ok(Expr::Block { statements })
+    }
+
     fn block(&mut self) -> Result<Vec<Stmt>, RloxError> {
+        self.descend("Block too deeply nested.", Self::unnested_block)
+    }
+
+    fn unnested_block(&mut self) -> Result<Vec<Stmt>, RloxError> {
         let mut statements: Vec<Stmt> = vec![];
+        let in_then_branch = std::mem::replace(&mut self.in_then_branch, false);
 
         while !self.check(TokenType::RightBrace) && !self.is_end() {
-            statements.push(self.declaration()?);
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(err) => {
+                    self.in_then_branch = in_then_branch;
+                    return Err(err);
+                }
+            }
         }
+        self.in_then_branch = in_then_branch;
 
         self.consume(TokenType::RightBrace, "Expect '}' after block.".to_string())?;
 
@@ -330,11 +544,15 @@ impl Parser {
     }
 
     fn if_statement(&mut self) -> Result<Stmt, RloxError> {
+        let line = self.previous().line;
         self.consume(TokenType::LeftParen, "Expect '(' after block.".to_string())?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after block.".to_string())?;
 
-        let then_branch = self.statement()?;
+        let in_then_branch = std::mem::replace(&mut self.in_then_branch, true);
+        let then_branch = self.statement();
+        self.in_then_branch = in_then_branch;
+        let then_branch = then_branch?;
         let else_branch: Option<Box<Stmt>> = if self.match_token(vec![TokenType::Else]) {
             let inner_statement = self.statement()?;
             Some(Box::new(inner_statement))
@@ -342,12 +560,35 @@ impl Parser {
             None
         };
         Ok(Stmt::If {
+            line,
             condition,
             then_branch: Box::new(then_branch),
             else_branch,
         })
     }
 
+    // `x |> f` is sugar for `f(x)`, left-associative so `x |> f |> g` is `g(f(x))`
+    fn pipe(&mut self) -> Result<Expr, RloxError> {
+        let mut expr = self.or()?;
+        while self.match_token(vec![TokenType::PipeGreater]) {
+            let paren = self.previous();
+            let callee = self.or()?;
+            if let Expr::Call { .. } = callee {
+                return Err(RloxError::ParseError {
+                    current: self.current,
+                    token: paren,
+                    message: "Expect a function without arguments after '|>'.".to_string(),
+                });
+            }
+            expr = Expr::Call {
+                callee: Box::new(callee),
+                paren,
+                arguments: Box::new(vec![expr]),
+            }
+        }
+        Ok(expr)
+    }
+
     fn or(&mut self) -> Result<Expr, RloxError> {
         let mut expr = self.and()?;
         while self.match_token(vec![TokenType::Or]) {
@@ -377,15 +618,164 @@ impl Parser {
     }
 
     fn while_statement(&mut self) -> Result<Stmt, RloxError> {
+        let line = self.previous().line;
         self.consume(TokenType::LeftParen, "Expect '(' after block.".to_string())?;
         let condition =self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after block.".to_string())?;
         let body = Box::new(self.statement()?);
+        let else_branch = self.loop_else(&body)?;
 
-        Ok(Stmt::While { condition, body })
+        Ok(Stmt::While {
+            line,
+            condition,
+            body,
+            else_branch,
+        })
+    }
+
+    // `else` after a loop's block body runs when the loop ends without `break`,
+    // inside an if's then branch the `else` stays the if's, as it was before loops had one
+    fn loop_else(&mut self, body: &Stmt) -> Result<Option<Box<Stmt>>, RloxError> {
+        if matches!(body, Stmt::Block { .. })
+            && !self.in_then_branch
+            && self.match_token(vec![TokenType::Else])
+        {
+            Ok(Some(Box::new(self.statement()?)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // `loop body` runs until a `break` or `return`, the same as `while (true) body`
+    fn loop_statement(&mut self) -> Result<Stmt, RloxError> {
+        let line = self.previous().line;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::While {
+            line,
+            condition: Expr::Boolean(true),
+            body,
+            else_branch: None,
+        })
+    }
+
+    fn do_while_statement(&mut self) -> Result<Stmt, RloxError> {
+        let line = self.previous().line;
+        let body = Box::new(self.statement()?);
+        self.consume(TokenType::While, "Expect 'while' after do body.".to_string())?;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.".to_string())?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.".to_string())?;
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after do-while condition.".to_string(),
+        )?;
+
+        Ok(Stmt::DoWhile {
+            line,
+            body,
+            condition,
+        })
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous();
+        self.consume(TokenType::LeftBrace, "Expect '{' after 'try'.".to_string())?;
+        let body = self.block()?;
+        self.consume(TokenType::Catch, "Expect 'catch' after try block.".to_string())?;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'catch'.".to_string())?;
+        let name = self.consume(
+            TokenType::Identifier,
+            "Expect error variable name.".to_string(),
+        )?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after error variable.".to_string(),
+        )?;
+        self.consume(TokenType::LeftBrace, "Expect '{' after catch clause.".to_string())?;
+        let handler = self.block()?;
+
+        Ok(Stmt::Try {
+            keyword,
+            body,
+            name,
+            handler,
+        })
+    }
+
+    // cases don't fall through, `break` leaves the switch early
+    fn switch_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'switch'.".to_string())?;
+        let subject = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after switch value.".to_string(),
+        )?;
+        self.consume(
+            TokenType::LeftBrace,
+            "Expect '{' before switch cases.".to_string(),
+        )?;
+
+        let mut cases = vec![];
+        let mut default = None;
+        while !self.check(TokenType::RightBrace) && !self.is_end() {
+            if self.match_token(vec![TokenType::Case]) {
+                let value = self.expression()?;
+                let guard = if self.match_token(vec![TokenType::If]) {
+                    Some(Box::new(self.expression()?))
+                } else {
+                    None
+                };
+                self.consume(TokenType::Colon, "Expect ':' after case value.".to_string())?;
+                let body = self.case_body()?;
+                cases.push(CaseClause { value, guard, body });
+            } else if self.match_token(vec![TokenType::Default]) {
+                let token = self.previous();
+                self.consume(TokenType::Colon, "Expect ':' after 'default'.".to_string())?;
+                if default.is_some() {
+                    return Err(RloxError::ParseError {
+                        current: self.current,
+                        token,
+                        message: "Switch can't have more than one default case.".to_string(),
+                    });
+                }
+                default = Some(self.case_body()?);
+            } else {
+                return Err(RloxError::ParseError {
+                    current: self.current,
+                    token: self.peek(),
+                    message: "Expect 'case' or 'default'.".to_string(),
+                });
+            }
+        }
+        self.consume(
+            TokenType::RightBrace,
+            "Expect '}' after switch cases.".to_string(),
+        )?;
+
+        Ok(Stmt::Switch {
+            keyword,
+            subject,
+            cases,
+            default,
+        })
+    }
+
+    fn case_body(&mut self) -> Result<Vec<Stmt>, RloxError> {
+        let mut statements = vec![];
+        while !self.check(TokenType::Case)
+            && !self.check(TokenType::Default)
+            && !self.check(TokenType::RightBrace)
+            && !self.is_end()
+        {
+            statements.push(self.declaration()?);
+        }
+        Ok(statements)
     }
 
     fn for_statement(&mut self) -> Result<Stmt, RloxError> {
+        let line = self.previous().line;
         self.consume(TokenType::LeftParen, "Expect '(' after for.".to_string())?;
         let initializer = if self.match_token(vec![TokenType::Semicolon]) {
             None
@@ -395,7 +785,7 @@ impl Parser {
             Some(self.expression_statement()?)
         };
 
-        let mut condition = if !self.check(TokenType::Semicolon) {
+        let condition = if !self.check(TokenType::Semicolon) {
             Some(self.expression()?)
         } else {
             None
@@ -417,37 +807,56 @@ impl Parser {
             "Expect ')' after for clause.".to_string(),
         )?;
 
-        let mut body = self.statement()?;
+        let body = self.statement()?;
+        let else_branch = self.loop_else(&body)?;
 
-        if let Some(inc) = increment {
-            body = Stmt::Block {
-                statements: vec![body, Stmt::Expression { expression: inc }],
-            }
-        }
-        if condition.is_none() {
-            condition = Some(Expr::Boolean(false))
-        }
-        body = Stmt::While {
-            condition: condition.expect("cannot be none we just set the value"),
+        Ok(Stmt::For {
+            line,
+            initializer: initializer.map(Box::new),
+            condition,
+            increment,
             body: Box::new(body),
-        };
+            else_branch,
+        })
+    }
 
-        if let Some(init) = initializer {
-            body = Stmt::Block {
-                statements: vec![init, body],
-            }
+    // `++x` gives back the value `x` has after the step
+    fn increment(&self, target: Expr, operator: Token) -> Result<Expr, RloxError> {
+        let Expr::Variable { name, .. } = target else {
+            return Err(RloxError::ParseError {
+                current: self.current,
+                token: operator,
+                message: "Invalid increment target.".to_string(),
+            });
         };
-        Ok(body)
+        Ok(Expr::Prefix {
+            id: next_id(),
+            name,
+            operator,
+        })
     }
 
     fn call(&mut self) -> Result<Expr, RloxError> {
         let mut expr = self.primary()?;
 
-        loop {
-            match self.match_token(vec![TokenType::LeftParen]) {
-                true => expr = self.finish_call(expr)?,
-                false => break,
-            }
+        while self.match_token(vec![TokenType::LeftParen]) {
+            expr = self.finish_call(expr)?;
+        }
+        if self.match_token(vec![TokenType::PlusPlus, TokenType::MinusMinus]) {
+            // `x++` gives back the value `x` had before the step
+            let operator = self.previous();
+            let Expr::Variable { name, .. } = expr else {
+                return Err(RloxError::ParseError {
+                    current: self.current,
+                    token: operator,
+                    message: "Invalid increment target.".to_string(),
+                });
+            };
+            return Ok(Expr::Postfix {
+                id: next_id(),
+                name,
+                operator,
+            });
         }
         Ok(expr)
     }
@@ -465,19 +874,20 @@ impl Parser {
                     });
                 }
                 arguments.push(self.expression()?);
-                if !self.match_token(vec![TokenType::Comma]) {
+                if !self.match_token(vec![TokenType::Comma]) || self.check(TokenType::RightParen) {
                     break;
                 }
             }
         }
 
-        self.consume(
+        let paren = self.consume(
             TokenType::RightParen,
             "Expected ')' after arguments".to_string(),
         )?;
 
         Ok(Expr::Call {
             callee: Box::new(expr),
+            paren,
             arguments: Box::new(arguments),
         })
     }
@@ -493,7 +903,7 @@ impl Parser {
             format!("Expect '(' after {kind} name.").to_string(),
         )?;
 
-        let mut parameters: Vec<String> = vec![];
+        let mut parameters: Vec<Token> = vec![];
 
         if !self.check(TokenType::RightParen) {
             loop {
@@ -506,9 +916,9 @@ impl Parser {
                 }
                 parameters.push(self.consume(
                     TokenType::Identifier,
-                    format!("Expect parameter name.").to_string(),
-                )?.lexeme);
-                if !self.match_token(vec![TokenType::Comma]) {
+                    "Expect parameter name.".to_string(),
+                )?);
+                if !self.match_token(vec![TokenType::Comma]) || self.check(TokenType::RightParen) {
                     break;
                 }
             }
@@ -516,7 +926,7 @@ impl Parser {
 
         self.consume(
             TokenType::RightParen,
-            format!("Expect ')' after parameters.").to_string(),
+            "Expect ')' after parameters.".to_string(),
         )?;
 
         self.consume(
@@ -524,16 +934,64 @@ impl Parser {
             format!("Expect '{{' before {kind} body.").to_string(),
         )?;
 
-        let body = Rc::new(self.block()?);
+        // labels don't reach into a nested function body
+        let enclosing_labels = std::mem::take(&mut self.labels);
+        let body = self.block();
+        self.labels = enclosing_labels;
+        let body = Rc::new(body?);
 
         Ok(Stmt::Function {
+            line: name.line,
             name: name.lexeme,
             body,
             parameters: Rc::new(parameters),
         })
     }
 
+    fn break_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous();
+        let label = if self.check(TokenType::Identifier) {
+            let label = self.advance();
+            if !self.labels.contains(&label.lexeme) {
+                return Err(RloxError::ParseError {
+                    current: self.current,
+                    message: format!("No enclosing loop labeled '{}'.", label.lexeme),
+                    token: label,
+                });
+            }
+            Some(label)
+        } else {
+            None
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after 'break'.".to_string())?;
+        Ok(Stmt::Break { keyword, label })
+    }
+
+    fn labeled_statement(&mut self) -> Result<Stmt, RloxError> {
+        let label = self.advance();
+        self.consume(TokenType::Colon, "Expect ':' after label.".to_string())?;
+        if !self.check(TokenType::While)
+            && !self.check(TokenType::For)
+            && !self.check(TokenType::Do)
+            && !self.check(TokenType::Loop)
+        {
+            return Err(RloxError::ParseError {
+                current: self.current,
+                token: self.peek(),
+                message: "Expect a loop after label.".to_string(),
+            });
+        }
+        self.labels.push(label.lexeme.clone());
+        let body = self.statement();
+        self.labels.pop();
+        Ok(Stmt::Labeled {
+            label,
+            body: Box::new(body?),
+        })
+    }
+
     fn return_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous();
         let value = if !self.check(TokenType::Semicolon) {
             Some(self.expression()?)
         } else {
@@ -544,6 +1002,223 @@ impl Parser {
             TokenType::Semicolon,
             "expected ';' after return value".to_string(),
         )?;
-        Ok(Stmt::Return { value })
+        Ok(Stmt::Return { keyword, value })
+    }
+}
+
+impl Stmt {
+    // the source line the statement starts on
+    pub fn line(&self) -> usize {
+        match self {
+            Stmt::Assert { keyword, .. }
+            | Stmt::Break { keyword, .. }
+            | Stmt::Continue { keyword }
+            | Stmt::Defer { keyword, .. }
+            | Stmt::Return { keyword, .. }
+            | Stmt::Switch { keyword, .. }
+            | Stmt::Try { keyword, .. }
+            | Stmt::Throw { keyword, .. } => keyword.line,
+            Stmt::Labeled { label, .. } => label.line,
+            Stmt::Block { line, .. }
+            | Stmt::Expression { line, .. }
+            | Stmt::If { line, .. }
+            | Stmt::Function { line, .. }
+            | Stmt::Print { line, .. }
+            | Stmt::Var { line, .. }
+            | Stmt::For { line, .. }
+            | Stmt::DoWhile { line, .. }
+            | Stmt::While { line, .. } => *line,
+        }
+    }
+}
+
+// literal expressions as `{"value": 3}`, a tagged newtype variant must be an object
+#[cfg(feature = "serde")]
+pub mod literal {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Literal<T> {
+        value: T,
+    }
+
+    pub fn serialize<T: Serialize, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        Literal { value }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        Literal::deserialize(deserializer).map(|literal| literal.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Parser;
+    use crate::ast_printer::AstPrinter;
+    use crate::error::RloxError;
+    use crate::test_interpreter::TestInterpreter;
+
+    fn printed(source: &str) -> Vec<String> {
+        let mut lox = TestInterpreter::new();
+        lox.run(source).unwrap();
+        lox.printed_lines().to_vec()
+    }
+
+    #[test]
+    fn loop_else_runs_after_a_block_body() {
+        assert_eq!(printed("while (false) {} else print 1;"), ["1"]);
+        assert_eq!(printed("for (var i = 0; i < 2; i = i + 1) { break; } else print 1; print 2;"), ["2"]);
+    }
+
+    #[test]
+    fn else_after_a_loop_in_a_then_branch_belongs_to_the_if() {
+        assert_eq!(printed(r#"if (false) while (false) print 1; else print "if";"#), ["if"]);
+        assert_eq!(printed(r#"if (false) while (false) {} else print "if";"#), ["if"]);
+        assert_eq!(printed(r#"if (true) { while (false) {} else print "loop"; }"#), ["loop"]);
+    }
+
+    // test threads have a small stack, so these use a lower limit than the default
+    fn parse_error(source: &str) -> String {
+        match Parser::from_source(source).unwrap().with_max_depth(30).parse() {
+            Err(RloxError::ParseError { message, .. }) => message,
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn deep_blocks_are_a_parse_error() {
+        let depth = 100_000;
+        let nested = |open: &str, inner: &str, close: &str| {
+            format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth))
+        };
+        for source in [
+            nested("{", "", "}"),
+            format!("print {};", nested("{", "1", "}")),
+            nested("fun f() {", "", "}"),
+            nested("if (true) ", "print 1;", ""),
+        ] {
+            assert!(parse_error(&source).ends_with("too deeply nested."));
+        }
+    }
+
+    #[test]
+    fn nested_blocks_still_parse() {
+        let source = format!("{}print 1;{}", "{".repeat(20), "}".repeat(20));
+        assert_eq!(printed(&source), ["1"]);
+        let source = format!("print {}1{};", "{ 1 + ".repeat(20), "}".repeat(20));
+        assert_eq!(printed(&source), ["21"]);
+    }
+
+    #[test]
+    fn loop_else_needs_a_block_body() {
+        let mut lox = TestInterpreter::new();
+        assert!(lox.run("while (false) print 1; else print 2;").is_err());
+    }
+
+    fn printed_expression(source: &str) -> String {
+        let expression = Parser::from_source(source).unwrap().parse_expression().unwrap();
+        AstPrinter {}.print(&expression)
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(printed_expression("a or b and c"), "(or a (and b c))");
+        assert_eq!(printed_expression("a and b or c"), "(or (and a b) c)");
+        assert_eq!(printed_expression("a or b or c"), "(or (or a b) c)");
+        assert_eq!(printed_expression("a = b or c"), "(= a (or b c))");
+        assert_eq!(
+            printed("print true or false and false; print false and true or true; print nil or false and 1;"),
+            ["true", "true", "false"]
+        );
+    }
+
+    #[test]
+    fn assignment_takes_the_logical_result() {
+        assert_eq!(printed("var a; a = nil or 2; print a; a = 1 and nil; print a;"), ["2", "nil"]);
+    }
+
+    #[test]
+    fn unary_operators_are_kept() {
+        assert_eq!(printed_expression("-1"), "(- 1)");
+        assert_eq!(printed_expression("!!true"), "(! (! true))");
+        assert_eq!(printed("print -1; print !true; print - -2; print -(1 + 2);"), ["-1", "false", "2", "-3"]);
+    }
+
+    #[test]
+    fn unclosed_input_is_a_parse_error() {
+        for (source, message) in [
+            ("{ print 1;", "Expect '}' after block."),
+            ("fun f(a, b", "Expect ')' after parameters."),
+            ("print (1 + 2", "Expect ')' after expression."),
+        ] {
+            assert_eq!(parse_error(source), message, "for {:?}", source);
+        }
+    }
+
+    #[test]
+    fn line_comments_end_at_the_line_or_the_input() {
+        assert_eq!(printed("print 1; // one\nprint 2; // two"), ["1", "2"]);
+        assert_eq!(printed("print 3; //"), ["3"]);
+    }
+
+
+    fn expression_error(source: &str) -> String {
+        match Parser::from_source(source).unwrap().parse_expression() {
+            Err(RloxError::ParseError { token, message, .. }) => format!("{} {}", token, message),
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn parse_expression_reads_a_single_expression() {
+        assert_eq!(printed_expression("1 + 2 * 3"), "(+ 1 (* 2 3))");
+        assert_eq!(printed_expression("f(x)"), "(call f x)");
+    }
+
+    #[test]
+    fn parse_expression_rejects_anything_after_the_expression() {
+        assert_eq!(expression_error("1 + 2 3"), "Number(3) Expect end of expression.");
+        assert_eq!(expression_error("1;"), "Semicolon Expect end of expression.");
+    }
+
+    #[test]
+    fn parse_expression_rejects_empty_input() {
+        assert_eq!(expression_error(""), "Eof Expect expression.");
+    }
+
+
+    #[test]
+    fn lone_open_paren_is_a_parse_error() {
+        assert_eq!(parse_error("("), "Expect expression.");
+        assert_eq!(expression_error("("), "Eof Expect expression.");
+    }
+
+
+    #[test]
+    fn switch_keeps_its_default_apart_from_the_cases() {
+        use crate::stmt::Stmt;
+
+        let source = "switch (x) { default: print 0; print 1; case 1: print 2; }";
+        let statements = Parser::from_source(source).unwrap().parse().unwrap();
+        match &statements[..] {
+            [Stmt::Switch { cases, default: Some(default), .. }] => {
+                assert_eq!(cases.len(), 1);
+                assert_eq!(default.len(), 2);
+            }
+            statements => panic!("expected a switch with a default, got {:?}", statements),
+        }
+        assert_eq!(
+            parse_error("switch (x) { default: print 0; default: print 1; }"),
+            "Switch can't have more than one default case."
+        );
+        assert_eq!(parse_error("switch (x) { default print 0; }"), "Expect ':' after 'default'.");
+    }
+
+
+    #[test]
+    fn typeof_binds_tighter_than_arithmetic() {
+        assert_eq!(printed_expression("typeof 1 + 1"), "(+ (typeof 1) 1)");
+        assert_eq!(printed_expression("typeof (1 + 1)"), "(typeof (group (+ 1 1)))");
+        assert_eq!(printed_expression("typeof x == \"number\""), "(== (typeof x) number)");
     }
 }
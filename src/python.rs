@@ -0,0 +1,137 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use pyo3::exceptions::{PyRuntimeError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyBytes, PyFloat, PyInt, PyString};
+
+use crate::error::RloxError;
+use crate::interpreter::{Interpreter, Value};
+use crate::optimizer::fold_constants;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::stmt::Stmt;
+
+// an interpreter for Python, keeping its globals between calls
+#[pyclass(unsendable)]
+pub struct PyLox {
+    interpreter: Interpreter,
+    output: Rc<RefCell<Vec<u8>>>,
+}
+
+#[pymethods]
+impl PyLox {
+    #[new]
+    fn new() -> Self {
+        let output = Rc::new(RefCell::new(vec![]));
+        Self {
+            interpreter: Interpreter::new().with_output(output.clone()),
+            output,
+        }
+    }
+
+    // runs a program and gives back what it printed, raising RuntimeError on failure
+    fn run(&mut self, source: &str) -> PyResult<String> {
+        let statements = self.prepare(source, false)?;
+        let result = self.interpreter.interpret(&statements);
+        let output = std::mem::take(&mut *self.output.borrow_mut());
+        result.map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    // runs a program and gives back the value of its trailing expression, what it prints is dropped
+    fn eval(&mut self, py: Python<'_>, source: &str) -> PyResult<Py<PyAny>> {
+        let statements = self.prepare(source, true)?;
+        let result = self.interpreter.eval(&statements);
+        self.output.borrow_mut().clear();
+        let value = result.map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+        to_python(py, &value)
+    }
+
+    // ints, floats, strings, bools, bytes and None can be passed in
+    fn define_global(&mut self, name: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        // bool before int, Python's bools are ints
+        let value = if value.is_none() {
+            Value::Nil
+        } else if value.is_instance_of::<PyBool>() {
+            Value::Bool(value.extract()?)
+        } else if value.is_instance_of::<PyInt>() {
+            Value::Integer(value.extract()?)
+        } else if value.is_instance_of::<PyFloat>() {
+            Value::Number(value.extract()?)
+        } else if value.is_instance_of::<PyString>() {
+            Value::Str(value.extract::<String>()?.into())
+        } else if value.is_instance_of::<PyBytes>() {
+            Value::Bytes(Rc::new(value.extract()?))
+        } else {
+            return Err(PyTypeError::new_err(format!(
+                "Can't use a {} as a Lox value.",
+                value.get_type().name()?
+            )));
+        };
+        self.interpreter.set_global(name, value);
+        Ok(())
+    }
+}
+
+impl PyLox {
+    fn prepare(&mut self, source: &str, trailing_expression: bool) -> PyResult<Vec<Stmt>> {
+        let error = |message: String| PyRuntimeError::new_err(message);
+        let mut parser = Parser::from_source(source).map_err(|errors| {
+            error(
+                errors
+                    .iter()
+                    .map(RloxError::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+        })?;
+        let mut statements = if trailing_expression {
+            parser.parse_with_trailing_expression()
+        } else {
+            parser.parse()
+        }
+        .map_err(|err| error(err.to_string()))?;
+        fold_constants(&mut statements);
+        Resolver::new(&mut self.interpreter)
+            .resolve(&statements)
+            .map_err(|err| error(err.to_string()))?;
+        Ok(statements)
+    }
+}
+
+// integers stay ints, functions become their printed form
+fn to_python(py: Python<'_>, value: &Value) -> PyResult<Py<PyAny>> {
+    let object = match value {
+        Value::Integer(number) => number.into_pyobject(py)?.into_any(),
+        Value::Number(number) => number.into_pyobject(py)?.into_any(),
+        Value::Str(string) => string.as_ref().into_pyobject(py)?.into_any(),
+        Value::Bool(boolean) => boolean.into_pyobject(py)?.to_owned().into_any(),
+        Value::Bytes(bytes) => PyBytes::new(py, bytes).into_any(),
+        Value::Nil => py.None().into_bound(py),
+        Value::Func(_) => value.to_string().into_pyobject(py)?.into_any(),
+    };
+    Ok(object.unbind())
+}
+
+#[pymodule]
+fn rlox(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyLox>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PyLox;
+    use pyo3::prelude::*;
+
+    #[test]
+    fn eval_output_doesnt_leak_into_the_next_run() {
+        Python::initialize();
+        Python::attach(|py| {
+            let mut lox = PyLox::new();
+            let value = lox.eval(py, "print 1; 2").unwrap();
+            assert_eq!(value.extract::<i64>(py).unwrap(), 2);
+            assert_eq!(lox.run("print 3;").unwrap(), "3\n");
+        });
+    }
+}
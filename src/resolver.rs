@@ -2,32 +2,50 @@ use std::collections::HashMap;
 use std::ops::Deref;
 use std::rc::Rc;
 
-use crate::{error::*, expr::*, interpreter::*, stmt::*};
+use crate::{error::*, expr::*, interpreter::*, parser::CaseClause, scanner::Token, stmt::*};
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 enum FunctionType {
     None,
     Function,
 }
-pub struct Resolver {
-    pub interpreter: Interpreter,
+pub struct Resolver<'a> {
+    interpreter: &'a mut Interpreter,
     scopes: Vec<HashMap<String, bool>>,
     current_function: FunctionType,
     depth_map: HashMap<usize, usize>,
+    // loops and switches around the current statement, inside the current function
+    loop_depth: usize,
+    switch_depth: usize,
+    // inside a deferred statement, which runs as its function exits and can't jump out of it
+    in_defer: bool,
+    // per scope, the locals that haven't been read yet and the line they were declared on
+    unused: Vec<HashMap<String, usize>>,
+    warnings: Vec<Warning>,
 }
 
-impl Resolver {
-    pub fn new(interpreter: Interpreter) -> Self {
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Self {
         Self {
             interpreter,
             scopes: Vec::new(),
             depth_map: HashMap::new(),
             current_function: FunctionType::None,
+            loop_depth: 0,
+            switch_depth: 0,
+            in_defer: false,
+            unused: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 
     fn begin_scope(&mut self) {
-        self.scopes.push(HashMap::new())
+        self.scopes.push(HashMap::new());
+        self.unused.push(HashMap::new());
+    }
+
+    pub fn warnings(&self) -> Vec<Warning> {
+        self.warnings.clone()
     }
     pub fn resolve(&mut self, statements: &Vec<Stmt>) -> Result<(), RloxError> {
         self.resolve_statements(statements)?;
@@ -36,27 +54,54 @@ impl Resolver {
         Ok(())
     }
     pub fn resolve_statements(&mut self, statements: &Vec<Stmt>) -> Result<(), RloxError> {
+        let mut exited = false;
         for statement in statements.deref() {
+            if exited {
+                self.warnings.push(Warning::UnreachableCode {
+                    line: statement.line(),
+                });
+            }
             self.resolve_statement(statement)?;
+            // only a direct exit counts, one nested in an `if` or loop may not run
+            exited |= matches!(
+                statement,
+                Stmt::Return { .. } | Stmt::Break { .. } | Stmt::Continue { .. } | Stmt::Throw { .. }
+            );
         }
         Ok(())
     }
 
     fn resolve_statement(&mut self, statement: &Stmt) -> Result<(), RloxError> {
         match statement {
-            Stmt::Block { statements } => {
+            Stmt::Block { statements, .. } => {
                 self.begin_scope();
                 self.resolve_statements(statements.as_ref())?;
                 self.end_scope();
             }
-            Stmt::Expression { expression } => {
+            Stmt::Expression { expression, .. } => {
                 self.resolve_expression(expression)?;
             }
-            Stmt::Print { expression } => {
+            Stmt::Print { expression, .. } => {
                 self.resolve_expression(expression)?;
             }
-            Stmt::Var { name, initializer } => {
+            Stmt::Throw { value, .. } => {
+                self.resolve_expression(value)?;
+            }
+            Stmt::Assert {
+                condition, message, ..
+            } => {
+                self.resolve_expression(condition)?;
+                if let Some(message) = message {
+                    self.resolve_expression(message)?;
+                }
+            }
+            Stmt::Var {
+                line,
+                name,
+                initializer,
+            } => {
                 self.declare(name);
+                self.track_unused(name, *line);
                 if let Some(init) = initializer {
                     self.resolve_expression(init)?;
                 };
@@ -66,6 +111,7 @@ impl Resolver {
                 condition,
                 then_branch,
                 else_branch,
+                ..
             } => {
                 self.resolve_expression(condition)?;
                 self.resolve_statement(then_branch.as_ref())?;
@@ -73,22 +119,146 @@ impl Resolver {
                     self.resolve_statement(stmt)?;
                 }
             }
-            Stmt::While { condition, body } => {
+            Stmt::While {
+                condition,
+                body,
+                else_branch,
+                ..
+            } => {
                 self.resolve_expression(condition)?;
-                self.resolve_statement(body.as_ref())?;
+                self.resolve_loop_body(body)?;
+                // outside the loop, a `break` in it leaves an enclosing one
+                if let Some(else_branch) = else_branch {
+                    self.resolve_statement(else_branch)?;
+                }
+            }
+            Stmt::DoWhile {
+                body, condition, ..
+            } => {
+                self.resolve_loop_body(body)?;
+                self.resolve_expression(condition)?;
+            }
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                else_branch,
+                ..
+            } => {
+                self.begin_scope();
+                if let Some(initializer) = initializer {
+                    self.resolve_statement(initializer)?;
+                }
+                if let Some(condition) = condition {
+                    self.resolve_expression(condition)?;
+                }
+                if let Some(increment) = increment {
+                    self.resolve_expression(increment)?;
+                }
+                self.resolve_loop_body(body)?;
+                if let Some(else_branch) = else_branch {
+                    self.resolve_statement(else_branch)?;
+                }
+                self.end_scope();
+            }
+            Stmt::Switch {
+                subject,
+                cases,
+                default,
+                ..
+            } => {
+                self.resolve_expression(subject)?;
+                self.switch_depth += 1;
+                let result = self.resolve_cases(cases, default);
+                self.switch_depth -= 1;
+                result?;
+            }
+            Stmt::Try {
+                body,
+                name,
+                handler,
+                ..
+            } => {
+                self.begin_scope();
+                self.resolve_statements(body)?;
+                self.end_scope();
+                self.begin_scope();
+                self.declare(&name.lexeme);
+                self.define(&name.lexeme);
+                self.resolve_statements(handler)?;
+                self.end_scope();
+            }
+            Stmt::Break { keyword, .. } => {
+                if self.loop_depth == 0 && self.switch_depth == 0 && self.in_defer {
+                    return Err(RloxError::ResolveError {
+                        line: keyword.line,
+                        message: "Can't use 'break' to leave a deferred statement.".to_string(),
+                    });
+                }
+                if self.loop_depth == 0 && self.switch_depth == 0 {
+                    return Err(RloxError::ResolveError {
+                        line: keyword.line,
+                        message: "Can't use 'break' outside a loop.".to_string(),
+                    });
+                }
+            }
+            Stmt::Continue { keyword } => {
+                if self.loop_depth == 0 && self.in_defer {
+                    return Err(RloxError::ResolveError {
+                        line: keyword.line,
+                        message: "Can't use 'continue' to leave a deferred statement.".to_string(),
+                    });
+                }
+                if self.loop_depth == 0 {
+                    return Err(RloxError::ResolveError {
+                        line: keyword.line,
+                        message: "Can't use 'continue' outside a loop.".to_string(),
+                    });
+                }
+            }
+            Stmt::Labeled { body, .. } => {
+                self.resolve_statement(body)?;
+            }
+            Stmt::Defer { keyword, body } => {
+                if self.current_function == FunctionType::None {
+                    return Err(RloxError::ResolveError {
+                        line: keyword.line,
+                        message: "Can't defer outside of a function.".to_string(),
+                    });
+                }
+                // loops around the defer have ended by the time it runs
+                let enclosing_loop_depth = std::mem::take(&mut self.loop_depth);
+                let enclosing_switch_depth = std::mem::take(&mut self.switch_depth);
+                let enclosing_defer = std::mem::replace(&mut self.in_defer, true);
+                let result = self.resolve_statement(body);
+                self.loop_depth = enclosing_loop_depth;
+                self.switch_depth = enclosing_switch_depth;
+                self.in_defer = enclosing_defer;
+                result?;
             }
             Stmt::Function {
                 name,
                 parameters,
                 body,
+                ..
             } => {
                 self.declare(name);
                 self.define(name);
                 self.resolve_function(name, parameters, body, FunctionType::Function)?;
             }
-            Stmt::Return { value } => {
+            Stmt::Return { keyword, value } => {
                 if self.current_function == FunctionType::None {
-                    return Err(RloxError::InterpreterError);
+                    return Err(RloxError::ResolveError {
+                        line: keyword.line,
+                        message: "Can't return from top-level code.".to_string(),
+                    });
+                }
+                if self.in_defer {
+                    return Err(RloxError::ResolveError {
+                        line: keyword.line,
+                        message: "Can't return from a deferred statement.".to_string(),
+                    });
                 }
                 if let Some(val) = value {
                     self.resolve_expression(val)?;
@@ -109,7 +279,9 @@ impl Resolver {
                 self.resolve_expression(left)?;
                 self.resolve_expression(right)?;
             }
-            Expr::Call { callee, arguments } => {
+            Expr::Call {
+                callee, arguments, ..
+            } => {
                 self.resolve_expression(callee)?;
                 for arg in arguments.as_ref() {
                     self.resolve_expression(arg)?;
@@ -118,6 +290,11 @@ impl Resolver {
             Expr::Grouping { expression } => {
                 self.resolve_expression(expression)?;
             }
+            Expr::Block { statements } => {
+                self.begin_scope();
+                self.resolve_statements(statements)?;
+                self.end_scope();
+            }
             Expr::Unary { operator: _, right } => {
                 self.resolve_expression(right)?;
             }
@@ -131,15 +308,18 @@ impl Resolver {
             }
             Expr::Variable { id, name } => {
                 if let Some(local) = self.scopes.last() {
-                    if local.get::<str>(name) == Some(&false) {
+                    if local.get::<str>(&name.lexeme) == Some(&false) {
                         return Err(RloxError::InterpreterError);
                     }
-                    self.resolve_local(*id, name);
+                    self.resolve_local(*id, &name.lexeme, true);
                 }
             }
             Expr::Assign { id, name, value } => {
                 self.resolve_expression(value)?;
-                self.resolve_local(*id, name);
+                self.resolve_local(*id, &name.lexeme, false);
+            }
+            Expr::Postfix { id, name, .. } | Expr::Prefix { id, name, .. } => {
+                self.resolve_local(*id, &name.lexeme, true);
             }
             _ => {}
         }
@@ -147,30 +327,44 @@ impl Resolver {
     }
     fn end_scope(&mut self) {
         self.scopes.pop();
+        if let Some(unused) = self.unused.pop() {
+            let mut unused: Vec<_> = unused.into_iter().collect();
+            unused.sort_by_key(|(_, line)| *line);
+            for (name, line) in unused {
+                self.warnings.push(Warning::UnusedVariable { name, line });
+            }
+        }
+    }
+
+    // remembers a local `var` or parameter until something reads it
+    fn track_unused(&mut self, name: &str, line: usize) {
+        if let Some(unused) = self.unused.last_mut() {
+            unused.insert(name.to_string(), line);
+        }
     }
 
     fn declare(&mut self, name: &str) {
-        match self.scopes.last_mut() {
-            Some(scope) => {
-                scope.insert(name.to_string(), false);
-            }
-            None => {}
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), false);
         }
     }
 
     fn define(&mut self, name: &str) {
-        match self.scopes.last_mut() {
-            Some(scope) => {
-                scope.insert(name.to_string(), true);
-            }
-            None => {}
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), true);
         }
     }
 
-    fn resolve_local(&mut self, depth: usize, name: &str) {
+    // `read` is false for assignments, which don't count as a use
+    fn resolve_local(&mut self, depth: usize, name: &str, read: bool) {
         for (index, scope) in self.scopes.iter().rev().enumerate() {
             if scope.contains_key(name) {
                 self.depth_map.insert(depth, index);
+                if read {
+                    let scope = self.scopes.len() - 1 - index;
+                    self.unused[scope].remove(name);
+                }
+                return;
             }
         }
     }
@@ -178,23 +372,137 @@ impl Resolver {
     fn resolve_function(
         &mut self,
         name: &str,
-        parameters: &Vec<String>,
+        parameters: &Vec<Token>,
         body: &Rc<Vec<Stmt>>,
         function_type: FunctionType,
     ) -> Result<(), RloxError> {
+        for (index, parameter) in parameters.iter().enumerate() {
+            if parameters[..index]
+                .iter()
+                .any(|earlier| earlier.lexeme == parameter.lexeme)
+            {
+                return Err(RloxError::ResolveError {
+                    line: parameter.line,
+                    message: format!(
+                        "Already a parameter named '{}' in this scope.",
+                        parameter.lexeme
+                    ),
+                });
+            }
+        }
         self.declare(name);
         self.define(name);
         let enclosing_function = self.current_function;
         self.current_function = function_type;
+        // a loop around the declaration doesn't reach into the body
+        let enclosing_loop_depth = std::mem::take(&mut self.loop_depth);
+        let enclosing_switch_depth = std::mem::take(&mut self.switch_depth);
+        let enclosing_defer = std::mem::take(&mut self.in_defer);
         self.begin_scope();
         for token in parameters {
-            self.declare(&token);
-            self.define(&token);
+            self.declare(&token.lexeme);
+            self.define(&token.lexeme);
+            self.track_unused(&token.lexeme, token.line);
         }
 
-        self.resolve_statements(&body)?;
+        let result = self.resolve_statements(body);
         self.end_scope();
         self.current_function = enclosing_function;
+        self.loop_depth = enclosing_loop_depth;
+        self.switch_depth = enclosing_switch_depth;
+        self.in_defer = enclosing_defer;
+        result
+    }
+
+    fn resolve_cases(
+        &mut self,
+        cases: &Vec<CaseClause>,
+        default: &Option<Vec<Stmt>>,
+    ) -> Result<(), RloxError> {
+        for case in cases {
+            self.resolve_expression(&case.value)?;
+            if let Some(guard) = &case.guard {
+                self.resolve_expression(guard)?;
+            }
+            self.begin_scope();
+            self.resolve_statements(&case.body)?;
+            self.end_scope();
+        }
+        if let Some(default) = default {
+            self.begin_scope();
+            self.resolve_statements(default)?;
+            self.end_scope();
+        }
         Ok(())
     }
+
+    fn resolve_loop_body(&mut self, body: &Stmt) -> Result<(), RloxError> {
+        self.loop_depth += 1;
+        let result = self.resolve_statement(body);
+        self.loop_depth -= 1;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Resolver;
+    use crate::error::{RloxError, Warning};
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::test_interpreter::TestInterpreter;
+
+    fn resolve_error(source: &str) -> String {
+        match TestInterpreter::new().run(source) {
+            Err(RloxError::ResolveError { message, .. }) => message,
+            result => panic!("expected a resolve error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn deferred_statements_cant_jump_out() {
+        assert_eq!(
+            resolve_error("fun f() { while (true) { defer break; return 1; } }"),
+            "Can't use 'break' to leave a deferred statement."
+        );
+        assert_eq!(
+            resolve_error("fun f() { while (true) { defer continue; return 1; } }"),
+            "Can't use 'continue' to leave a deferred statement."
+        );
+        assert_eq!(
+            resolve_error("fun f() { defer return 2; return 1; }"),
+            "Can't return from a deferred statement."
+        );
+    }
+
+    #[test]
+    fn deferred_statements_can_loop_and_declare_functions() {
+        let mut lox = TestInterpreter::new();
+        lox.run(
+            "fun f() {
+                defer { while (true) break; fun g() { return 2; } print g(); }
+                print 1;
+            }
+            f();",
+        )
+        .unwrap();
+        assert_eq!(lox.printed_lines(), ["1", "2"]);
+    }
+
+    fn warnings(source: &str) -> Vec<Warning> {
+        let statements = Parser::from_source(source).unwrap().parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        let mut resolver = Resolver::new(&mut interpreter);
+        resolver.resolve(&statements).unwrap();
+        resolver.warnings()
+    }
+
+    #[test]
+    fn code_after_throw_is_unreachable() {
+        assert_eq!(
+            warnings("fun f() {\n throw 1;\n print 2;\n}"),
+            [Warning::UnreachableCode { line: 3 }]
+        );
+        assert_eq!(warnings("fun f(x) {\n if (x) throw 1;\n print 2;\n}"), []);
+    }
 }
@@ -1,16 +1,22 @@
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
+use std::rc::Rc;
 
 use crate::error::*;
 
 #[derive(Debug, Clone)]
 pub struct Scanner {
-    source: Vec<u8>,
+    source: Vec<char>,
     tokens: Vec<Token>,
     start: usize,
     current: usize,
+    // byte offset of `start` in the source text
+    start_byte: usize,
     line: usize,
     keywords: HashMap<String, TokenType>,
+    case_insensitive_keywords: bool,
+    source_name: Option<Rc<str>>,
 }
 impl Default for Scanner {
     fn default() -> Self {
@@ -19,47 +25,103 @@ impl Default for Scanner {
             tokens: vec![],
             start: 0,
             current: 0,
+            start_byte: 0,
             line: 1,
             keywords: vec![
                 ("and", TokenType::And),
+                ("assert", TokenType::Assert),
+                ("break", TokenType::Break),
+                ("case", TokenType::Case),
+                ("catch", TokenType::Catch),
                 ("class", TokenType::Class),
+                ("continue", TokenType::Continue),
+                ("default", TokenType::Default),
+                ("defer", TokenType::Defer),
+                ("do", TokenType::Do),
                 ("else", TokenType::Else),
                 ("false", TokenType::False),
                 ("for", TokenType::For),
                 ("fun", TokenType::Fun),
                 ("if", TokenType::If),
+                ("loop", TokenType::Loop),
                 ("nil", TokenType::Nil),
                 ("or", TokenType::Or),
                 ("print", TokenType::Print),
                 ("return", TokenType::Return),
                 ("super", TokenType::Super),
+                ("switch", TokenType::Switch),
                 ("this", TokenType::This),
+                ("throw", TokenType::Throw),
                 ("true", TokenType::True),
+                ("try", TokenType::Try),
+                ("typeof", TokenType::Typeof),
                 ("var", TokenType::Var),
                 ("while", TokenType::While),
             ]
             .into_iter()
             .map(|(k, v)| (String::from(k), v))
             .collect(),
+            case_insensitive_keywords: false,
+            source_name: None,
         }
     }
 }
 impl Scanner {
-    pub fn scan_tokens(&mut self, input: String) -> Result<Vec<Token>, RloxError> {
-        self.source = input.into_bytes();
+    // the file name every token is tagged with, for error messages
+    pub fn with_source_name(mut self, name: &str) -> Self {
+        self.source_name = Some(name.into());
+        self
+    }
+
+    // lets `PRINT` or `While` scan as keywords, off by default
+    pub fn with_case_insensitive_keywords(mut self, enabled: bool) -> Self {
+        self.case_insensitive_keywords = enabled;
+        self
+    }
+
+    // on failure returns every scan error in the input, not just the first
+    pub fn scan_tokens(&mut self, input: String) -> Result<Vec<Token>, Vec<RloxError>> {
+        self.scan(input)?;
+        Ok(self
+            .tokens
+            .iter()
+            .filter(|token| !token.token_type.is_trivia())
+            .cloned()
+            .collect())
+    }
+
+    // keeps whitespace and comments so that concatenating every lexeme gives back the source
+    pub fn tokenize_with_trivia(&mut self, input: String) -> Result<Vec<Token>, Vec<RloxError>> {
+        self.scan(input)?;
+        Ok(self.tokens.to_vec())
+    }
+
+    fn scan(&mut self, input: String) -> Result<(), Vec<RloxError>> {
+        self.source = input.chars().collect();
+        let mut errors = vec![];
         while !self.is_end() {
+            self.start_byte += self.byte_len(self.start, self.current);
             self.start = self.current;
-            self.scan_token()?;
+            if let Err(err) = self.scan_token() {
+                errors.push(err);
+            }
         }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let end_byte = self.start_byte + self.byte_len(self.start, self.current);
         self.tokens.push({
             Token {
                 token_type: TokenType::Eof,
                 lexeme: "".to_string(),
                 literal: None,
                 line: self.line,
+                start_byte: end_byte,
+                end_byte,
+                source_name: self.source_name.clone(),
             }
         });
-        Ok(self.tokens.to_vec())
+        Ok(())
     }
     fn is_end(&self) -> bool {
         self.current >= self.source.len()
@@ -73,9 +135,22 @@ impl Scanner {
             '{' => self.add_token(TokenType::LeftBrace, None),
             '}' => self.add_token(TokenType::RightBrace, None),
             ',' => self.add_token(TokenType::Comma, None),
+            ':' => self.add_token(TokenType::Colon, None),
             '.' => self.add_token(TokenType::Dot, None),
-            '-' => self.add_token(TokenType::Minus, None),
-            '+' => self.add_token(TokenType::Plus, None),
+            '-' => {
+                if self.match_next_token('-') {
+                    self.add_token(TokenType::MinusMinus, None)
+                } else {
+                    self.add_token(TokenType::Minus, None)
+                }
+            }
+            '+' => {
+                if self.match_next_token('+') {
+                    self.add_token(TokenType::PlusPlus, None)
+                } else {
+                    self.add_token(TokenType::Plus, None)
+                }
+            }
             ';' => self.add_token(TokenType::Semicolon, None),
             '*' => self.add_token(TokenType::Star, None),
             '!' => {
@@ -108,33 +183,28 @@ impl Scanner {
             }
             '/' => {
                 if self.match_next_token('/') {
-                    loop {
-                        let ch = self.peek();
-                        if ch != '\n' || ch != '\0' {
-                            self.advance();
-                        } else {
-                            break;
-                        }
-                    }
-                    Ok(())
+                    self.line_comment()
+                } else if self.match_next_token('*') {
+                    self.block_comment()
                 } else {
                     self.add_token(TokenType::Slash, None)
                 }
             }
-            '"' => self.string(),
+            '|' if self.match_next_token('>') => self.add_token(TokenType::PipeGreater, None),
+            '"' => self.string('"'),
+            // raw strings, which can hold double quotes. Lox strings have no escape sequences,
+            // so this is only another delimiter, both kinds keep their text and newlines as is
+            '`' => self.string('`'),
             '0'..='9' => self.number(),
-            ' ' | '\r' | '\t' => Ok(()),
-            '\n' => {
-                self.line += 1;
-                Ok(())
-            }
+            ' ' | '\r' | '\t' | '\n' => self.whitespace(token),
             _ => {
-                if token.is_alphabetic() {
+                if token.is_alphabetic() || token == '_' {
                     self.identifier()
                 } else {
                     Err(RloxError::ScanError {
+                        line: self.line,
                         character: token,
-                        message: "unhandled token {}".to_string(),
+                        message: "Unexpected character.".to_string(),
                     })
                 }
             }
@@ -142,22 +212,33 @@ impl Scanner {
     }
     fn advance(&mut self) -> char {
         self.current += 1;
-        char::from(self.source[self.current - 1])
+        self.source[self.current - 1]
     }
 
     fn add_token(&mut self, token: TokenType, literal: Option<Literal>) -> Result<(), RloxError> {
-        let lexeme = String::from_utf8(self.source[self.start..self.current].to_owned()).unwrap();
+        let lexeme = self.text(self.start, self.current);
         self.tokens.push(Token {
             token_type: token,
+            start_byte: self.start_byte,
+            end_byte: self.start_byte + lexeme.len(),
+            source_name: self.source_name.clone(),
             lexeme,
             literal,
             line: self.line,
         });
-        return Ok(());
+        Ok(())
     }
+    fn text(&self, start: usize, end: usize) -> String {
+        self.source[start..end].iter().collect()
+    }
+    // the source is kept as chars, tokens point into it by bytes
+    fn byte_len(&self, start: usize, end: usize) -> usize {
+        self.source[start..end].iter().map(|c| c.len_utf8()).sum()
+    }
+
     fn match_next_token(&mut self, match_token: char) -> bool {
         match self.source.get(self.current) {
-            Some(ch) if *ch as char == match_token => {
+            Some(ch) if *ch == match_token => {
                 self.current += 1;
                 true
             }
@@ -169,10 +250,51 @@ impl Scanner {
         if self.is_end() {
             return '\0';
         }
-        char::from(self.source[self.current])
+        self.source[self.current]
+    }
+    fn whitespace(&mut self, first: char) -> Result<(), RloxError> {
+        if first == '\n' {
+            self.line += 1;
+        }
+        while matches!(self.peek(), ' ' | '\r' | '\t' | '\n') {
+            if self.advance() == '\n' {
+                self.line += 1;
+            }
+        }
+        self.add_token(TokenType::Whitespace, None)
+    }
+
+    fn line_comment(&mut self) -> Result<(), RloxError> {
+        while self.peek() != '\n' && !self.is_end() {
+            self.advance();
+        }
+        let text = self.text(self.start + 2, self.current);
+        self.add_token(TokenType::LineComment(text), None)
+    }
+
+    fn block_comment(&mut self) -> Result<(), RloxError> {
+        while !(self.peek() == '*' && self.peek_next() == '/') {
+            if self.is_end() {
+                return Err(RloxError::ScanError {
+                    line: self.line,
+                    character: '/',
+                    message: "Unterminated block comment.".to_string(),
+                });
+            }
+            if self.advance() == '\n' {
+                self.line += 1;
+            }
+        }
+        self.advance();
+        self.advance();
+        let text = self.text(self.start + 2, self.current - 2);
+        self.add_token(TokenType::BlockComment(text), None)
     }
-    fn string(&mut self) -> Result<(), RloxError> {
-        while self.peek() != '"' && !self.is_end() {
+
+    // everything up to the closing `quote` as is, newlines included
+    fn string(&mut self, quote: char) -> Result<(), RloxError> {
+        let start_line = self.line;
+        while self.peek() != quote && !self.is_end() {
             if self.peek() == '\n' {
                 self.line += 1;
             }
@@ -180,38 +302,57 @@ impl Scanner {
         }
         if self.is_end() {
             return Err(RloxError::UnterminatedStringError {
-                token: String::from_utf8(self.source[self.start..self.current - 1].to_vec())
-                    .expect("valid string range"),
-                message: "unhandled: unterminated string".to_string(),
+                line: start_line,
+                token: self.text(self.start, self.current),
+                message: "Unterminated string.".to_string(),
             });
         }
 
         self.advance();
-        let value = self.source[self.start + 1..self.current - 1].to_owned();
-        let string_literal: &str = &String::from_utf8(value)
-            .expect("should be a valid string passed.")
-            .to_lowercase();
-        let (token_type, literal) = match string_literal {
-            "true" => (TokenType::True, Some(Literal::True)),
-            "false" => (TokenType::False, Some(Literal::False)),
-            "null" => (TokenType::Nil, Some(Literal::Nil)),
-            v => (TokenType::String, Some(Literal::Str(v.to_owned()))),
-        };
-        self.add_token(token_type, literal)
+        let value = self.text(self.start + 1, self.current - 1);
+        self.add_token(TokenType::String, Some(Literal::Str(value)))
     }
 
+    // digits alone make an integer, a fraction or exponent makes a float
     fn number(&mut self) -> Result<(), RloxError> {
         while self.peek().is_ascii_digit() {
             self.advance();
         }
+        let digits_only = !(self.peek() == '.' && self.peek_next().is_ascii_digit())
+            && self.peek() != 'e'
+            && self.peek() != 'E';
+        if digits_only {
+            // too big for an i64, fall back to a float
+            if let Ok(integer) = self.text(self.start, self.current).parse() {
+                return self.add_token(TokenType::Number, Some(Literal::Integer(integer)));
+            }
+        }
         if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             self.advance();
             while self.peek().is_ascii_digit() {
                 self.advance();
             }
         }
-        let value = self.source[self.start..self.current].to_owned();
-        let number_value: f64 = String::from_utf8(value).unwrap().parse().unwrap();
+        if self.peek() == 'e' || self.peek() == 'E' {
+            self.advance();
+            if self.peek() == '+' || self.peek() == '-' {
+                self.advance();
+            }
+            if !self.peek().is_ascii_digit() {
+                return Err(RloxError::ScanError {
+                    line: self.line,
+                    character: self.peek(),
+                    message: "Malformed number exponent.".to_string(),
+                });
+            }
+            while self.peek().is_ascii_digit() {
+                self.advance();
+            }
+        }
+        let number_value: f64 = self
+            .text(self.start, self.current)
+            .parse()
+            .expect("scanned digits parse as a number");
         self.add_token(TokenType::Number, Some(Literal::Number(number_value)))
     }
 
@@ -219,16 +360,20 @@ impl Scanner {
         if self.current + 1 >= self.source.len() {
             return '\0';
         }
-        char::from(self.source[self.current + 1])
+        self.source[self.current + 1]
     }
 
     fn identifier(&mut self) -> Result<(), RloxError> {
-        while self.peek().is_ascii_alphanumeric() {
+        while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
             self.advance();
         }
-        let value = self.source[self.start..self.current].to_owned();
-        let string_value = String::from_utf8(value).unwrap();
-        match self.keywords.get(&string_value) {
+        let string_value = self.text(self.start, self.current);
+        let keyword = if self.case_insensitive_keywords {
+            self.keywords.get(&string_value.to_lowercase())
+        } else {
+            self.keywords.get(&string_value)
+        };
+        match keyword {
             Some(keyword) => self.add_token(keyword.to_owned(), None),
             None => self.add_token(
                 TokenType::Identifier,
@@ -243,6 +388,7 @@ impl Scanner {
 //     scanner.scan_tokens(input)
 // }
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenType {
     // Single-character tokens.
     LeftParen,
@@ -250,6 +396,7 @@ pub enum TokenType {
     LeftBrace,
     RightBrace,
     Comma,
+    Colon,
     Dot,
     Minus,
     Plus,
@@ -266,6 +413,9 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    MinusMinus,
+    PipeGreater,
+    PlusPlus,
 
     // Literals.
     Identifier,
@@ -274,40 +424,84 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Assert,
+    Break,
+    Case,
+    Catch,
     Class,
+    Continue,
+    Default,
+    Defer,
+    Do,
     Else,
     False,
     Fun,
     For,
     If,
+    Loop,
     Nil,
     Or,
     Print,
     Return,
     Super,
+    Switch,
     This,
+    Throw,
     True,
+    Try,
+    Typeof,
     Var,
     While,
 
+    // Trivia, only kept by `tokenize_with_trivia`.
+    Whitespace,
+    LineComment(String),
+    BlockComment(String),
+
     Eof,
 }
 
+impl TokenType {
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            TokenType::Whitespace | TokenType::LineComment(_) | TokenType::BlockComment(_)
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Literal {
     Identifier(String),
     Str(String),
+    Integer(i64),
     Number(f64),
     True,
     False,
     Nil,
 }
 
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::Identifier(i) => write!(f, "{}", i),
+            Literal::Str(s) => write!(f, "{:?}", s),
+            Literal::Integer(n) => write!(f, "{}", n),
+            Literal::Number(n) => write!(f, "{}", n),
+            Literal::True => write!(f, "true"),
+            Literal::False => write!(f, "false"),
+            Literal::Nil => write!(f, "nil"),
+        }
+    }
+}
+
 impl Hash for Literal {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
             Literal::Identifier(i) => i.hash(state),
             Literal::Str(s) => s.hash(state),
+            Literal::Integer(n) => n.hash(state),
             Literal::Number(n) => n.to_bits().hash(state),
             Literal::True => true.hash(state),
             Literal::False => false.hash(state),
@@ -322,6 +516,7 @@ impl PartialEq for Literal {
         match (self, other) {
             (Literal::Identifier(fst), Literal::Identifier(snd)) => fst.eq(snd),
             (Literal::Str(fst), Literal::Str(snd)) => fst.eq(snd),
+            (Literal::Integer(fst), Literal::Integer(snd)) => fst.eq(snd),
             (Literal::Number(fst), Literal::Number(snd)) => fst.eq(snd),
             (Literal::True, Literal::True) => true,
             (Literal::False, Literal::False) => true,
@@ -332,9 +527,192 @@ impl PartialEq for Literal {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<Literal>,
     pub line: usize,
+    // where the lexeme sits in the source text, as a byte range
+    pub start_byte: usize,
+    pub end_byte: usize,
+    // the file the token was read from, if the scanner was given one
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub source_name: Option<Rc<str>>,
+}
+
+impl Token {
+    // `file.lox:12` when the file is known, `line 12` otherwise
+    pub fn location(&self) -> String {
+        match &self.source_name {
+            Some(name) => format!("{}:{}", name, self.line),
+            None => format!("line {}", self.line),
+        }
+    }
+}
+
+// Identifier("myVar"), Number(3.14), If, ...
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (&self.token_type, &self.literal) {
+            (TokenType::Identifier, _) => write!(f, "Identifier({:?})", self.lexeme),
+            (TokenType::String | TokenType::Number, Some(literal)) => {
+                write!(f, "{:?}({})", self.token_type, literal)
+            }
+            (token_type, _) => write!(f, "{:?}", token_type),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Scanner, TokenType};
+    use crate::error::RloxError;
+    use crate::parser::Parser;
+    use crate::test_interpreter::TestInterpreter;
+
+    #[test]
+    fn trivia_round_trips_the_source() {
+        let source = "  var a = 1; // one\n/* two\n three */ print a;\t// end";
+        let tokens = Scanner::default().tokenize_with_trivia(source.to_string()).unwrap();
+        let lexemes: String = tokens.iter().map(|t| t.lexeme.as_str()).collect();
+        assert_eq!(lexemes, source);
+        assert!(tokens.iter().any(|t| t.token_type == TokenType::BlockComment(" two\n three ".to_string())));
+        let plain = Scanner::default().scan_tokens(source.to_string()).unwrap();
+        assert!(plain.iter().all(|t| !t.token_type.is_trivia()));
+    }
+
+    #[test]
+    fn keywords_ignore_case_only_when_asked() {
+        let scan = |enabled| {
+            Scanner::default()
+                .with_case_insensitive_keywords(enabled)
+                .scan_tokens("PRINT \"x\";".to_string())
+                .unwrap()
+        };
+        assert_eq!(scan(true)[0].token_type, TokenType::Print);
+        assert!(Parser::new(scan(true)).parse().is_ok());
+        assert_eq!(scan(false)[0].token_type, TokenType::Identifier);
+        assert!(Parser::new(scan(false)).parse().is_err());
+    }
+
+    #[test]
+    fn collects_every_scan_error() {
+        let errors = Scanner::default()
+            .scan_tokens("var a = 1 @ 2;\nprint \"open;".to_string())
+            .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], RloxError::ScanError { line: 1, character: '@', .. }));
+        assert!(matches!(errors[1], RloxError::UnterminatedStringError { line: 2, .. }));
+        assert_eq!(errors[0].to_string(), "[line 1] Error at '@': Unexpected character.");
+        assert_eq!(errors[1].to_string(), "[line 2] Error: Unterminated string.");
+    }
+
+    #[test]
+    fn byte_ranges_count_multibyte_characters() {
+        let source = "print \"héllo\" + x;";
+        let tokens = Scanner::default().scan_tokens(source.to_string()).unwrap();
+        let plus = tokens.iter().find(|t| t.token_type == TokenType::Plus).unwrap();
+        assert_eq!(&source[plus.start_byte..plus.end_byte], "+");
+        let x = tokens.iter().find(|t| t.lexeme == "x").unwrap();
+        assert_eq!((x.start_byte, x.end_byte), (17, 18));
+        assert_eq!(tokens.last().unwrap().start_byte, source.len());
+    }
+
+    #[test]
+    fn tokens_carry_the_source_name() {
+        let tokens = Scanner::default()
+            .with_source_name("main.lox")
+            .scan_tokens("\nprint 1;".to_string())
+            .unwrap();
+        assert_eq!(tokens[0].location(), "main.lox:2");
+        let unnamed = Scanner::default().scan_tokens("print 1;".to_string()).unwrap();
+        assert_eq!(unnamed[0].location(), "line 1");
+    }
+
+    #[test]
+    fn string_literals_keep_their_text() {
+        let tokens = Scanner::default()
+            .scan_tokens(r#"print "True"; print "null"; print "FALSE";"#.to_string())
+            .unwrap();
+        let strings: Vec<_> = tokens
+            .iter()
+            .filter(|t| t.token_type == TokenType::String)
+            .map(|t| t.lexeme.as_str())
+            .collect();
+        assert_eq!(strings, [r#""True""#, r#""null""#, r#""FALSE""#]);
+        let mut lox = TestInterpreter::new();
+        lox.run(r#"print "True"; print "null";"#).unwrap();
+        assert_eq!(lox.printed_lines(), ["True", "null"]);
+    }
+
+    #[test]
+    fn identifiers_can_hold_underscores() {
+        let tokens = Scanner::default().scan_tokens("_private snake_case_1".to_string()).unwrap();
+        let names: Vec<_> = tokens.iter().map(|t| (t.token_type.clone(), t.lexeme.as_str())).collect();
+        assert_eq!(
+            names,
+            [
+                (TokenType::Identifier, "_private"),
+                (TokenType::Identifier, "snake_case_1"),
+                (TokenType::Eof, "")
+            ]
+        );
+    }
+
+
+    fn string_literal(source: &str) -> String {
+        let tokens = Scanner::default().scan_tokens(source.to_string()).unwrap();
+        match &tokens[0].literal {
+            Some(super::Literal::Str(text)) => text.to_string(),
+            literal => panic!("expected a string, got {:?}", literal),
+        }
+    }
+
+    #[test]
+    fn raw_strings_span_lines_and_hold_double_quotes() {
+        assert_eq!(string_literal("`{\n  \"a\": 1\n}`"), "{\n  \"a\": 1\n}");
+        assert_eq!(string_literal(r#"`say "hi"`"#), r#"say "hi""#);
+        // backslashes are kept, as in double-quoted strings
+        assert_eq!(string_literal(r"`a\nb`"), r"a\nb");
+        let tokens = Scanner::default().scan_tokens("`one\ntwo\nthree` x".to_string()).unwrap();
+        assert_eq!(tokens[1].line, 3);
+        let mut lox = TestInterpreter::new();
+        lox.run("print `line \"one\"\nline two`;").unwrap();
+        assert_eq!(lox.printed_lines(), ["line \"one\"", "line two"]);
+    }
+
+    #[test]
+    fn unterminated_raw_string_is_a_scan_error() {
+        let errors = Scanner::default().scan_tokens("print 1;\n`open\nstill open".to_string()).unwrap_err();
+        assert!(
+            matches!(&errors[..], [RloxError::UnterminatedStringError { line: 2, .. }]),
+            "{:?}",
+            errors
+        );
+        assert_eq!(errors[0].to_string(), "[line 2] Error: Unterminated string.");
+    }
+
+
+    #[test]
+    fn numbers_can_have_exponents() {
+        for (source, value) in [("1e10", 1e10), ("2.5e-3", 2.5e-3), ("3E+2", 300.0), ("1.5E2", 150.0)] {
+            let tokens = Scanner::default().scan_tokens(source.to_string()).unwrap();
+            assert_eq!(tokens[0].literal, Some(super::Literal::Number(value)), "for {}", source);
+            assert_eq!(tokens[1].token_type, TokenType::Eof, "for {}", source);
+        }
+    }
+
+    #[test]
+    fn exponents_need_digits() {
+        for source in ["1e", "1e+", "2.5E-;"] {
+            let errors = Scanner::default().scan_tokens(source.to_string()).unwrap_err();
+            assert!(
+                matches!(&errors[..], [RloxError::ScanError { message, .. }] if message == "Malformed number exponent."),
+                "for {}: {:?}",
+                source,
+                errors
+            );
+        }
+    }
 }
@@ -0,0 +1,86 @@
+use std::{
+    cell::RefCell,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
+
+use crate::{
+    error::RloxError, interpreter::Interpreter, optimizer::fold_constants, parser::Parser,
+    resolver::Resolver,
+};
+
+// an interpreter that keeps what `print` writes instead of sending it to stdout
+pub struct TestInterpreter {
+    interpreter: Interpreter,
+    buffer: Rc<RefCell<Vec<u8>>>,
+    output: String,
+    lines: Vec<String>,
+}
+
+impl TestInterpreter {
+    pub fn new() -> Self {
+        Self::from_interpreter(Interpreter::new())
+    }
+
+    // for testing an interpreter set up with its builder methods, its output is replaced
+    pub fn from_interpreter(interpreter: Interpreter) -> Self {
+        let buffer = Rc::new(RefCell::new(vec![]));
+        Self {
+            interpreter: interpreter.with_output(buffer.clone()),
+            buffer,
+            output: String::new(),
+            lines: vec![],
+        }
+    }
+
+    // scans, parses, resolves and runs `source`
+    pub fn run(&mut self, source: &str) -> Result<(), RloxError> {
+        let result = self.run_source(source);
+        self.collect_output();
+        result
+    }
+
+    fn run_source(&mut self, source: &str) -> Result<(), RloxError> {
+        let mut parser = Parser::from_source(source).map_err(|mut errors| errors.remove(0))?;
+        let mut statements = parser.parse()?;
+        fold_constants(&mut statements);
+        Resolver::new(&mut self.interpreter).resolve(&statements)?;
+        self.interpreter.interpret(&statements)
+    }
+
+    // moves whatever was written since the last run into `output` and `lines`
+    fn collect_output(&mut self) {
+        let written = std::mem::take(&mut *self.buffer.borrow_mut());
+        let written = String::from_utf8_lossy(&written);
+        self.lines.extend(written.lines().map(str::to_string));
+        self.output.push_str(&written);
+    }
+
+    pub fn printed_lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn printed_output(&self) -> &str {
+        &self.output
+    }
+}
+
+impl Default for TestInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for TestInterpreter {
+    type Target = Interpreter;
+
+    fn deref(&self) -> &Interpreter {
+        &self.interpreter
+    }
+}
+
+impl DerefMut for TestInterpreter {
+    fn deref_mut(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+}
@@ -0,0 +1,119 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::RloxError;
+use crate::interpreter::{Interpreter, Value};
+use crate::optimizer::fold_constants;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::stmt::Stmt;
+
+// an interpreter for JS, keeping its globals between calls and collecting `print` output
+#[wasm_bindgen]
+pub struct WasmLox {
+    interpreter: Interpreter,
+    output: Rc<RefCell<Vec<u8>>>,
+}
+
+impl Default for WasmLox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl WasmLox {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        let output = Rc::new(RefCell::new(vec![]));
+        Self {
+            interpreter: Interpreter::new().with_output(output.clone()),
+            output,
+        }
+    }
+
+    // runs a program, giving back null or the error message
+    pub fn run(&mut self, source: &str) -> JsValue {
+        let result = self.prepare(source, false).and_then(|statements| {
+            self.interpreter
+                .interpret(&statements)
+                .map_err(|err| err.to_string())
+        });
+        match result {
+            Ok(()) => JsValue::NULL,
+            Err(message) => JsValue::from_str(&message),
+        }
+    }
+
+    // runs a program and gives back the value of its trailing expression, throwing the error message
+    pub fn eval(&mut self, source: &str) -> Result<JsValue, JsValue> {
+        let statements = self
+            .prepare(source, true)
+            .map_err(|message| JsValue::from_str(&message))?;
+        let value = self
+            .interpreter
+            .eval(&statements)
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
+        Ok(to_js(&value))
+    }
+
+    // numbers, strings, booleans, null and undefined can be passed in
+    pub fn define_global(&mut self, name: &str, value: JsValue) -> Result<(), JsValue> {
+        let value = if let Some(number) = value.as_f64() {
+            Value::Number(number)
+        } else if let Some(string) = value.as_string() {
+            Value::Str(string.into())
+        } else if let Some(boolean) = value.as_bool() {
+            Value::Bool(boolean)
+        } else if value.is_null() || value.is_undefined() {
+            Value::Nil
+        } else {
+            return Err(JsValue::from_str(
+                "Only numbers, strings, booleans and null can be Lox values.",
+            ));
+        };
+        self.interpreter.set_global(name, value);
+        Ok(())
+    }
+
+    // everything printed since the last call
+    pub fn take_output(&mut self) -> String {
+        String::from_utf8_lossy(&std::mem::take(&mut *self.output.borrow_mut())).into_owned()
+    }
+
+    fn prepare(&mut self, source: &str, trailing_expression: bool) -> Result<Vec<Stmt>, String> {
+        let messages = |errors: Vec<RloxError>| {
+            errors
+                .iter()
+                .map(RloxError::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let mut parser = Parser::from_source(source).map_err(messages)?;
+        let mut statements = if trailing_expression {
+            parser.parse_with_trailing_expression()
+        } else {
+            parser.parse()
+        }
+        .map_err(|err| err.to_string())?;
+        fold_constants(&mut statements);
+        Resolver::new(&mut self.interpreter)
+            .resolve(&statements)
+            .map_err(|err| err.to_string())?;
+        Ok(statements)
+    }
+}
+
+// integers become JS numbers, functions and bytes their printed form
+fn to_js(value: &Value) -> JsValue {
+    match value {
+        Value::Integer(number) => JsValue::from_f64(*number as f64),
+        Value::Number(number) => JsValue::from_f64(*number),
+        Value::Str(string) => JsValue::from_str(string),
+        Value::Bool(boolean) => JsValue::from_bool(*boolean),
+        Value::Nil => JsValue::NULL,
+        Value::Func(_) | Value::Bytes(_) => JsValue::from_str(&value.to_string()),
+    }
+}
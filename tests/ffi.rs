@@ -0,0 +1,45 @@
+// compiles tests/ffi_smoke.c against include/rlox.h and the cdylib, then runs it
+#![cfg(feature = "ffi")]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// builds the cdylib with only the ffi feature in a target dir of its own, the one in
+// target/debug is whichever feature set was built last
+fn library_dir() -> PathBuf {
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ffi");
+    let built = Command::new(env!("CARGO"))
+        .args(["build", "--lib", "--features", "ffi", "--target-dir"])
+        .arg(&target_dir)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .status()
+        .unwrap();
+    assert!(built.success(), "building the cdylib failed");
+    target_dir.join("debug")
+}
+
+#[test]
+fn c_program_runs_lox_through_the_header() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let library_dir = library_dir();
+    let executable = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ffi_smoke");
+    let compiled = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg(root.join("tests/ffi_smoke.c"))
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg("-L")
+        .arg(&library_dir)
+        .arg("-lrlox")
+        .arg("-o")
+        .arg(&executable)
+        .status()
+        .expect("a C compiler is needed for this test");
+    assert!(compiled.success(), "ffi_smoke.c didn't compile against include/rlox.h");
+
+    let ran = Command::new(&executable)
+        .env("LD_LIBRARY_PATH", &library_dir)
+        .env("DYLD_LIBRARY_PATH", &library_dir)
+        .status()
+        .unwrap();
+    assert!(ran.success(), "ffi_smoke exited with {}", ran);
+}
@@ -0,0 +1,28 @@
+/* Runs a couple of programs through the C API, exits non-zero on any mismatch. */
+#include <stdio.h>
+#include <string.h>
+
+#include "rlox.h"
+
+static int check(int condition, const char *what) {
+    if (!condition) {
+        fprintf(stderr, "ffi smoke test failed: %s\n", what);
+    }
+    return condition;
+}
+
+int main(void) {
+    Lox *lox = rlox_new();
+    const char *program = "var greeting = \"hello\"; print greeting;";
+    int ok = check(rlox_run(lox, program, strlen(program)) == 0, "valid program runs");
+    ok &= check(strcmp(rlox_get_output(lox), "hello\n") == 0, "output is captured");
+    ok &= check(rlox_get_error(lox) == NULL, "no error after success");
+
+    const char *broken = "print greeting + 1;";
+    ok &= check(rlox_run(lox, broken, strlen(broken)) == 1, "runtime error is reported");
+    ok &= check(rlox_get_error(lox) != NULL, "error message is set");
+
+    rlox_free(lox);
+    rlox_free(NULL);
+    return ok ? 0 : 1;
+}
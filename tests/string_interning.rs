@@ -0,0 +1,52 @@
+// measures what interning string literals saves, with an allocator that counts this thread's allocations
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use rlox::interpreter::Interpreter;
+use rlox::lox::Lox;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const ITERATIONS: usize = 1000;
+
+// allocations made while running a loop that evaluates the same literal each pass
+fn allocations(interning: bool) -> usize {
+    let interpreter = Interpreter::new()
+        .with_string_interning(interning)
+        .with_output(Rc::new(RefCell::new(std::io::sink())));
+    let mut lox = Lox::from_interpreter(interpreter);
+    let source = format!(
+        "var s; for (var i = 0; i < {}; i = i + 1) {{ s = \"the same text\"; }} print s;",
+        ITERATIONS
+    );
+    let before = ALLOCATIONS.with(Cell::get);
+    lox.run(&source).unwrap();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn interning_saves_an_allocation_per_evaluated_literal() {
+    let (pooled, fresh) = (allocations(true), allocations(false));
+    println!("{} iterations: {} allocations interned, {} without", ITERATIONS, pooled, fresh);
+    // the pool itself allocates a little on the first pass
+    assert!(fresh - pooled >= ITERATIONS - 10, "interned {}, not interned {}", pooled, fresh);
+}
@@ -0,0 +1,38 @@
+// exercises the JS-facing API, run with `cargo test --target wasm32-unknown-unknown --features wasm`
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use rlox::wasm::WasmLox;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn run_collects_what_the_program_prints() {
+    let mut lox = WasmLox::new();
+    assert!(lox.run("print 1 + 2;").is_null());
+    assert_eq!(lox.take_output(), "3\n");
+    assert_eq!(lox.take_output(), "");
+}
+
+#[wasm_bindgen_test]
+fn run_gives_back_the_error_message() {
+    let mut lox = WasmLox::new();
+    let error = lox.run("print nil + 1;").as_string().unwrap();
+    assert!(error.contains("Operands must be two numbers or two strings."), "{}", error);
+}
+
+#[wasm_bindgen_test]
+fn define_global_makes_values_visible_to_lox() {
+    let mut lox = WasmLox::new();
+    lox.define_global("name", JsValue::from_str("lox")).unwrap();
+    lox.define_global("count", JsValue::from_f64(2.5)).unwrap();
+    lox.define_global("flag", JsValue::TRUE).unwrap();
+    lox.define_global("nothing", JsValue::UNDEFINED).unwrap();
+    assert!(lox.run("print name; print count; print flag; print nothing;").is_null());
+    assert_eq!(lox.take_output(), "lox\n2.5\ntrue\nnil\n");
+}
+
+#[wasm_bindgen_test]
+fn define_global_rejects_other_values() {
+    let mut lox = WasmLox::new();
+    assert!(lox.define_global("big", JsValue::bigint_from_str("1")).is_err());
+}